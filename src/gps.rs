@@ -0,0 +1,334 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::ffi::CStr;
+use std::fmt;
+use std::ptr;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::rational::Rational;
+use crate::xmp::{PropFlags, Xmp};
+use crate::xmpvalue::{FromXmpValue, Property, ToXmpValue};
+use crate::DateTime;
+use crate::Result;
+
+/// The EXIF namespace, where the GPS properties are.
+fn ns_exif() -> &'static str {
+    // NS_EXIF is a constant NUL terminated string.
+    unsafe { CStr::from_ptr(ptr::addr_of!(c::NS_EXIF).cast()) }
+        .to_str()
+        .unwrap_or_default()
+}
+
+/// The reference (hemisphere) of a GPS coordinate.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum GpsRef {
+    /// North latitude.
+    North,
+    /// South latitude.
+    South,
+    /// East longitude.
+    East,
+    /// West longitude.
+    West,
+}
+
+impl GpsRef {
+    /// Return the letter used in XMP.
+    pub fn as_char(&self) -> char {
+        match *self {
+            GpsRef::North => 'N',
+            GpsRef::South => 'S',
+            GpsRef::East => 'E',
+            GpsRef::West => 'W',
+        }
+    }
+
+    /// Return the reference from the letter used in XMP.
+    pub fn from_char(c: char) -> Option<GpsRef> {
+        match c.to_ascii_uppercase() {
+            'N' => Some(GpsRef::North),
+            'S' => Some(GpsRef::South),
+            'E' => Some(GpsRef::East),
+            'W' => Some(GpsRef::West),
+            _ => None,
+        }
+    }
+
+    /// Whether the reference gives a negative decimal value.
+    fn is_negative(&self) -> bool {
+        matches!(*self, GpsRef::South | GpsRef::West)
+    }
+}
+
+/// A GPS coordinate, as stored in `exif:GPSLatitude` and
+/// `exif:GPSLongitude`.
+///
+/// XMP store them as `DDD,MM,SSk` or `DDD,MM.mmk`, where `k` is the
+/// reference. Both are parsed, and the second is written, the seconds
+/// being kept as the minutes fraction.
+///
+/// ```
+/// use exempi2::{GpsCoordinate, GpsRef};
+///
+/// let latitude: GpsCoordinate = "48,51.2046S".parse().unwrap();
+/// assert_eq!(latitude.reference, GpsRef::South);
+/// assert!((latitude.to_decimal() + 48.85341).abs() < 1e-9);
+/// assert_eq!(latitude.to_string(), "48,51.2046S");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsCoordinate {
+    /// Degrees.
+    pub degrees: u32,
+    /// Minutes, seconds included as the fraction.
+    pub minutes: f64,
+    /// Reference.
+    pub reference: GpsRef,
+}
+
+impl GpsCoordinate {
+    /// Latitude from decimal degrees. Negative is south.
+    pub fn from_latitude(value: f64) -> GpsCoordinate {
+        let reference = if value < 0.0 {
+            GpsRef::South
+        } else {
+            GpsRef::North
+        };
+        GpsCoordinate::from_decimal(value, reference)
+    }
+
+    /// Longitude from decimal degrees. Negative is west.
+    pub fn from_longitude(value: f64) -> GpsCoordinate {
+        let reference = if value < 0.0 {
+            GpsRef::West
+        } else {
+            GpsRef::East
+        };
+        GpsCoordinate::from_decimal(value, reference)
+    }
+
+    fn from_decimal(value: f64, reference: GpsRef) -> GpsCoordinate {
+        let value = value.abs();
+        let mut degrees = value.trunc() as u32;
+        // Round to what will be written out.
+        let mut minutes = ((value.fract() * 60.0) * 1e6).round() / 1e6;
+        if minutes >= 60.0 {
+            degrees += 1;
+            minutes -= 60.0;
+        }
+        GpsCoordinate {
+            degrees,
+            minutes,
+            reference,
+        }
+    }
+
+    /// Return the decimal degrees. South and west are negative.
+    pub fn to_decimal(&self) -> f64 {
+        let value = self.degrees as f64 + self.minutes / 60.0;
+        if self.reference.is_negative() {
+            -value
+        } else {
+            value
+        }
+    }
+}
+
+impl FromStr for GpsCoordinate {
+    type Err = Error;
+
+    /// Parse the coordinate. Return `BadValue` in case of error.
+    fn from_str(s: &str) -> Result<GpsCoordinate> {
+        let bad_value = || Error::from(c::XmpError::BadValue);
+
+        let s = s.trim();
+        let reference = s
+            .chars()
+            .last()
+            .and_then(GpsRef::from_char)
+            .ok_or_else(bad_value)?;
+        let parts: Vec<&str> = s[..s.len() - 1].split(',').map(str::trim).collect();
+        let (degrees, minutes, seconds) = match parts[..] {
+            [degrees, minutes] => (degrees, minutes, None),
+            [degrees, minutes, seconds] => (degrees, minutes, Some(seconds)),
+            _ => return Err(bad_value()),
+        };
+        let degrees = degrees.parse::<u32>().map_err(|_| bad_value())?;
+        let mut minutes = minutes.parse::<f64>().map_err(|_| bad_value())?;
+        if let Some(seconds) = seconds {
+            let seconds = seconds.parse::<f64>().map_err(|_| bad_value())?;
+            if !(0.0..60.0).contains(&seconds) || minutes.fract() != 0.0 {
+                return Err(bad_value());
+            }
+            minutes += seconds / 60.0;
+        }
+        if !(0.0..60.0).contains(&minutes) {
+            return Err(bad_value());
+        }
+        Ok(GpsCoordinate {
+            degrees,
+            minutes,
+            reference,
+        })
+    }
+}
+
+impl fmt::Display for GpsCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let minutes = format!("{:.6}", self.minutes);
+        let minutes = minutes.trim_end_matches('0').trim_end_matches('.');
+        write!(
+            f,
+            "{},{}{}",
+            self.degrees,
+            minutes,
+            self.reference.as_char()
+        )
+    }
+}
+
+impl FromXmpValue for GpsCoordinate {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<GpsCoordinate>>> {
+        String::from_xmp(xmp, schema, name)?
+            .map(|property| Ok(Property::new(property.value.parse()?, property.flags)))
+            .transpose()
+    }
+}
+
+impl ToXmpValue for GpsCoordinate {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        self.to_string().to_xmp(xmp, schema, name, optionbits)
+    }
+}
+
+/// A geographic location from the EXIF GPS properties.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeoPoint {
+    /// Latitude in decimal degrees. Negative is south.
+    pub latitude: f64,
+    /// Longitude in decimal degrees. Negative is west.
+    pub longitude: f64,
+    /// Altitude in meters. Negative is below sea level.
+    pub altitude: Option<f64>,
+    /// The GPS time stamp, UTC.
+    pub timestamp: Option<DateTime>,
+}
+
+impl Xmp {
+    /// Get the location from `exif:GPSLatitude`, `exif:GPSLongitude`,
+    /// `exif:GPSAltitude`, `exif:GPSAltitudeRef` and `exif:GPSTimeStamp`.
+    /// Latitude and longitude are required, the others are optional.
+    /// Return `Ok(None)` if there is no latitude or longitude.
+    pub fn get_geo_point(&self) -> Result<Option<GeoPoint>> {
+        let ns_exif = ns_exif();
+        let latitude = self.get::<GpsCoordinate>(ns_exif, "GPSLatitude")?;
+        let longitude = self.get::<GpsCoordinate>(ns_exif, "GPSLongitude")?;
+        let (latitude, longitude) = match (latitude, longitude) {
+            (Some(latitude), Some(longitude)) => (latitude, longitude),
+            _ => return Ok(None),
+        };
+        let altitude = match self.get::<Rational>(ns_exif, "GPSAltitude")? {
+            Some(altitude) => {
+                let altitude = altitude.to_f64();
+                // 1 is below sea level. Absent is above.
+                match self.get::<i32>(ns_exif, "GPSAltitudeRef")? {
+                    Some(1) => Some(-altitude),
+                    _ => Some(altitude),
+                }
+            }
+            None => None,
+        };
+        let timestamp = self.get::<DateTime>(ns_exif, "GPSTimeStamp")?;
+        Ok(Some(GeoPoint {
+            latitude: latitude.to_decimal(),
+            longitude: longitude.to_decimal(),
+            altitude,
+            timestamp,
+        }))
+    }
+
+    /// Set the EXIF GPS properties from the location.
+    /// The optional properties that are `None` are deleted.
+    /// Return `BadValue` if the location is out of range.
+    /// The change is atomic: on error the packet is left untouched.
+    pub fn set_geo_point(&mut self, point: &GeoPoint) -> Result<()> {
+        if !(-90.0..=90.0).contains(&point.latitude) || !(-180.0..=180.0).contains(&point.longitude)
+        {
+            return Err(Error::from(c::XmpError::BadValue));
+        }
+        let ns_exif = ns_exif();
+        let mut xmp = self.clone();
+        xmp.set(
+            ns_exif,
+            "GPSLatitude",
+            &GpsCoordinate::from_latitude(point.latitude),
+        )?;
+        xmp.set(
+            ns_exif,
+            "GPSLongitude",
+            &GpsCoordinate::from_longitude(point.longitude),
+        )?;
+        match point.altitude {
+            Some(altitude) if altitude.is_finite() => {
+                // Rational in centimeters.
+                let value = Rational::new((altitude.abs() * 100.0).round() as u32, 100).reduce();
+                xmp.set(ns_exif, "GPSAltitude", &value)?;
+                xmp.set(ns_exif, "GPSAltitudeRef", &i32::from(altitude < 0.0))?;
+            }
+            Some(_) => return Err(Error::from(c::XmpError::BadValue)),
+            None => {
+                xmp.delete_property(ns_exif, "GPSAltitude")?;
+                xmp.delete_property(ns_exif, "GPSAltitudeRef")?;
+            }
+        }
+        match point.timestamp {
+            Some(ref timestamp) => xmp.set(ns_exif, "GPSTimeStamp", timestamp)?,
+            None => xmp.delete_property(ns_exif, "GPSTimeStamp")?,
+        }
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GpsCoordinate, GpsRef};
+
+    #[test]
+    fn test_gps_coordinate() {
+        let coord: GpsCoordinate = "48,51.2046N".parse().unwrap();
+        assert_eq!(coord.degrees, 48);
+        assert_eq!(coord.minutes, 51.2046);
+        assert_eq!(coord.reference, GpsRef::North);
+        assert_eq!(coord.to_string(), "48,51.2046N");
+
+        let coord: GpsCoordinate = "48,51,12.3S".parse().unwrap();
+        assert_eq!(coord.reference, GpsRef::South);
+        assert!((coord.to_decimal() + (48.0 + 51.0 / 60.0 + 12.3 / 3600.0)).abs() < 1e-9);
+        assert_eq!(coord.to_string(), "48,51.205S");
+
+        let coord: GpsCoordinate = "2,0W".parse().unwrap();
+        assert_eq!(coord.to_decimal(), -2.0);
+        assert_eq!(coord.to_string(), "2,0W");
+
+        assert!("48,51.2046".parse::<GpsCoordinate>().is_err());
+        assert!("48,61N".parse::<GpsCoordinate>().is_err());
+        assert!("48,51.5,12N".parse::<GpsCoordinate>().is_err());
+        assert!("48N".parse::<GpsCoordinate>().is_err());
+        assert!("".parse::<GpsCoordinate>().is_err());
+
+        let coord = GpsCoordinate::from_latitude(-33.8688);
+        assert_eq!(coord.reference, GpsRef::South);
+        assert_eq!(coord.to_string(), "33,52.128S");
+        let coord = GpsCoordinate::from_longitude(151.2093);
+        assert_eq!(coord.reference, GpsRef::East);
+        assert!((coord.to_decimal() - 151.2093).abs() < 1e-9);
+        let coord = GpsCoordinate::from_longitude(-0.99999999999);
+        assert_eq!(coord.to_string(), "1,0W");
+    }
+}
@@ -10,10 +10,28 @@ extern crate exempi_sys as c;
 extern crate bitflags;
 
 mod error;
+mod gps;
+mod rational;
 mod xmp;
+mod xmpcanon;
+mod xmpdiff;
+mod xmpdump;
 mod xmpfile;
+mod xmpflat;
 mod xmpiterator;
+#[cfg(feature = "json")]
+mod xmpjson;
+mod xmpmerge;
+mod xmppath;
+mod xmpprefixed;
+mod xmprdf;
+#[cfg(feature = "serde")]
+pub mod xmpserde;
+mod xmpserial;
 mod xmpstring;
+mod xmptree;
+mod xmputils;
+mod xmpvalue;
 
 use std::cmp::Ordering;
 use std::ffi::CString;
@@ -26,14 +44,32 @@ pub use c::FileType;
 pub use c::TzSign;
 pub use c::XmpError;
 pub use error::Error;
-pub use xmp::{PropFlags, SerialFlags, Xmp};
+pub use gps::{GeoPoint, GpsCoordinate, GpsRef};
+pub use rational::{Rational, SignedRational};
+pub use xmp::{
+    ArrayForm, InsertPosition, PropFlags, RemoveFlags, SerialFlags, Xmp, ARRAY_LAST_ITEM,
+};
+pub use xmpdiff::XmpChange;
 pub use xmpfile::{CloseFlags, FormatOptionFlags, OpenFlags, XmpFile};
 pub use xmpiterator::{IterFlags, IterSkipFlags, XmpIterator};
+pub use xmpmerge::{MergeConflict, MergePolicy};
+pub use xmppath::{ToXmpPath, XmpPath};
+#[cfg(feature = "serde")]
+pub use xmpserde::{from_xmp, to_xmp};
+pub use xmpserial::SerialOptions;
 pub use xmpstring::XmpString;
+pub use xmptree::{XmpNode, XmpNodeKind, XmpSchema, XmpTree};
+pub use xmputils::ArrayItemsFlags;
+pub use xmpvalue::{FromXmpValue, Property, ToXmpValue};
 
 /// Result type
 pub type Result<T> = result::Result<T, Error>;
 
+/// The XML namespace, for xml:lang.
+pub(crate) const NS_XML_URI: &str = "http://www.w3.org/XML/1998/namespace";
+/// The RDF namespace, for rdf:type and the containers.
+pub(crate) const NS_RDF_URI: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
 static START: Once = Once::new();
 
 /// Initialize the library.
@@ -61,6 +97,17 @@ pub(crate) fn get_error() -> Error {
     })
 }
 
+/// Return `Ok(None)` if the last call failed because the property
+/// doesn't exist, the error otherwise.
+/// The toolkit doesn't raise an error for a missing property, so
+/// the error code is left to `Unknown`.
+pub(crate) fn absent_or_error<T>() -> Result<Option<T>> {
+    match get_error() {
+        Error(c::XmpError::Unknown) => Ok(None),
+        err => Err(err),
+    }
+}
+
 /// Register namespace with uri and suggested prefix
 /// Returns the actual registered prefix.
 pub fn register_namespace(uri: &str, prefix: &str) -> Result<XmpString> {
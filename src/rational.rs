@@ -0,0 +1,247 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::xmp::{PropFlags, Xmp};
+use crate::xmpvalue::{FromXmpValue, Property, ToXmpValue};
+use crate::Result;
+
+/// Greatest common divisor.
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// Parse `num/den`. A single integer is also accepted as `num/1`.
+fn parse_fraction<T: FromStr>(s: &str, one: T) -> Result<(T, T)> {
+    let s = s.trim();
+    let (num, den) = match s.split_once('/') {
+        Some((num, den)) => (
+            num.trim().parse().map_err(|_| bad_value())?,
+            den.trim().parse().map_err(|_| bad_value())?,
+        ),
+        None => (s.parse().map_err(|_| bad_value())?, one),
+    };
+    Ok((num, den))
+}
+
+fn bad_value() -> Error {
+    Error::from(c::XmpError::BadValue)
+}
+
+/// An unsigned rational number, like the EXIF and TIFF `RATIONAL`.
+/// In XMP it is stored as a string `num/den`, for example
+/// `exif:ExposureTime` or `tiff:XResolution`.
+///
+/// Equality is on the numerator and denominator: `1/2` and `2/4`
+/// are different. Use `reduce()` to compare values.
+///
+/// ```
+/// use exempi2::Rational;
+///
+/// let f_number: Rational = "28/10".parse().unwrap();
+/// assert_eq!(f_number.reduce(), Rational::new(14, 5));
+/// assert_eq!(f_number.to_f64(), 2.8);
+/// assert_eq!(f_number.to_string(), "28/10");
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Rational {
+    /// Numerator
+    pub num: u32,
+    /// Denominator
+    pub den: u32,
+}
+
+impl Rational {
+    /// New rational `num/den`.
+    pub fn new(num: u32, den: u32) -> Rational {
+        Rational { num, den }
+    }
+
+    /// Return the rational reduced to its lowest terms.
+    pub fn reduce(&self) -> Rational {
+        match gcd(self.num, self.den) {
+            0 => *self,
+            divisor => Rational::new(self.num / divisor, self.den / divisor),
+        }
+    }
+
+    /// Convert to a f64. A zero denominator gives infinity, or NaN for `0/0`.
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+impl From<u32> for Rational {
+    fn from(n: u32) -> Rational {
+        Rational::new(n, 1)
+    }
+}
+
+impl From<Rational> for f64 {
+    fn from(r: Rational) -> f64 {
+        r.to_f64()
+    }
+}
+
+impl FromStr for Rational {
+    type Err = Error;
+
+    /// Parse a rational. Return `BadValue` in case of error.
+    fn from_str(s: &str) -> Result<Rational> {
+        parse_fraction(s, 1).map(|(num, den)| Rational::new(num, den))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.num, self.den)
+    }
+}
+
+impl FromXmpValue for Rational {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<Rational>>> {
+        String::from_xmp(xmp, schema, name)?
+            .map(|property| Ok(Property::new(property.value.parse()?, property.flags)))
+            .transpose()
+    }
+}
+
+impl ToXmpValue for Rational {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        self.to_string().to_xmp(xmp, schema, name, optionbits)
+    }
+}
+
+/// A signed rational number, like the EXIF `SRATIONAL`.
+/// For example `exif:ExposureBiasValue`.
+///
+/// Like for `Rational`, equality is on the numerator and denominator.
+/// `reduce()` will also move the sign to the numerator.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SignedRational {
+    /// Numerator
+    pub num: i32,
+    /// Denominator
+    pub den: i32,
+}
+
+impl SignedRational {
+    /// New rational `num/den`.
+    pub fn new(num: i32, den: i32) -> SignedRational {
+        SignedRational { num, den }
+    }
+
+    /// Return the rational reduced to its lowest terms, with a
+    /// positive denominator.
+    pub fn reduce(&self) -> SignedRational {
+        let divisor = gcd(self.num.unsigned_abs(), self.den.unsigned_abs());
+        if divisor == 0 {
+            return *self;
+        }
+        // a divisor of 2^31 only happen with i32::MIN
+        let divisor = divisor as i64;
+        let mut num = self.num as i64 / divisor;
+        let mut den = self.den as i64 / divisor;
+        if den < 0 {
+            num = -num;
+            den = -den;
+        }
+        match (i32::try_from(num), i32::try_from(den)) {
+            (Ok(num), Ok(den)) => SignedRational::new(num, den),
+            _ => *self,
+        }
+    }
+
+    /// Convert to a f64. A zero denominator gives infinity, or NaN for `0/0`.
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+impl From<i32> for SignedRational {
+    fn from(n: i32) -> SignedRational {
+        SignedRational::new(n, 1)
+    }
+}
+
+impl From<SignedRational> for f64 {
+    fn from(r: SignedRational) -> f64 {
+        r.to_f64()
+    }
+}
+
+impl FromStr for SignedRational {
+    type Err = Error;
+
+    /// Parse a signed rational. Return `BadValue` in case of error.
+    fn from_str(s: &str) -> Result<SignedRational> {
+        parse_fraction(s, 1).map(|(num, den)| SignedRational::new(num, den))
+    }
+}
+
+impl fmt::Display for SignedRational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.num, self.den)
+    }
+}
+
+impl FromXmpValue for SignedRational {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<SignedRational>>> {
+        String::from_xmp(xmp, schema, name)?
+            .map(|property| Ok(Property::new(property.value.parse()?, property.flags)))
+            .transpose()
+    }
+}
+
+impl ToXmpValue for SignedRational {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        self.to_string().to_xmp(xmp, schema, name, optionbits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Rational, SignedRational};
+
+    #[test]
+    fn test_rational() {
+        assert_eq!("1/250".parse(), Ok(Rational::new(1, 250)));
+        assert_eq!(" 28 / 10 ".parse(), Ok(Rational::new(28, 10)));
+        assert_eq!("72".parse(), Ok(Rational::new(72, 1)));
+        assert!("1/".parse::<Rational>().is_err());
+        assert!("-1/2".parse::<Rational>().is_err());
+        assert!("a/b".parse::<Rational>().is_err());
+
+        assert_eq!(Rational::new(28, 10).reduce(), Rational::new(14, 5));
+        assert_eq!(Rational::new(0, 0).reduce(), Rational::new(0, 0));
+        assert_eq!(Rational::new(0, 5).reduce(), Rational::new(0, 1));
+        assert_eq!(Rational::new(1, 4).to_f64(), 0.25);
+        assert!(Rational::new(0, 0).to_f64().is_nan());
+        assert_eq!(Rational::new(1, 250).to_string(), "1/250");
+
+        assert_eq!("-1/3".parse(), Ok(SignedRational::new(-1, 3)));
+        assert_eq!(
+            SignedRational::new(2, -4).reduce(),
+            SignedRational::new(-1, 2)
+        );
+        assert_eq!(
+            SignedRational::new(i32::MIN, -1).reduce(),
+            SignedRational::new(i32::MIN, -1)
+        );
+        assert_eq!(SignedRational::new(-3, 2).to_f64(), -1.5);
+        assert_eq!(SignedRational::new(-3, 2).to_string(), "-3/2");
+    }
+}
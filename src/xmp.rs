@@ -9,9 +9,13 @@ use libc::c_char;
 use std::ffi::CString;
 
 use crate::error::Error;
+use crate::xmpiterator::{IterFlags, XmpIterator};
+use crate::xmppath::{ToXmpPath, XmpPath};
 use crate::xmpstring::XmpString;
+use crate::xmpvalue::{FromXmpValue, Property, ToXmpValue};
 use crate::DateTime;
 use crate::Result;
+use crate::NS_XML_URI;
 
 bitflags! {
     /// Property flags.
@@ -61,7 +65,6 @@ bitflags! {
         const IS_STABLE        = 0x0010_0000u32;
         /// This property is derived from the document content.
         const IS_DERIVED       = 0x0020_0000u32;
-        // kXMPUtil_AllowCommas   = 0x10000000u32,  ! Used by TXMPUtils::CatenateArrayItems and ::SeparateArrayItems.
         // kXMP_DeleteExisting    = 0x20000000u32,  ! Used by TXMPMeta::SetXyz functions to delete any pre-existing property.
         // kXMP_SchemaNode        = 0x80000000u32,  ! Returned by iterators - #define to avoid warnings
 
@@ -119,6 +122,95 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Options to remove the properties of schemas.
+    #[derive(Clone, Copy, Default, Debug, Eq, Hash, PartialEq)]
+    pub struct RemoveFlags: u32 {
+        /// Only remove the external properties.
+        const NONE = 0;
+        /// Also remove the internal properties, the ones with
+        /// `PropFlags::IS_INTERNAL`.
+        const INCLUDE_INTERNAL = 0x0001u32;
+        /// Also remove the aliases of the schema. This remove the actual
+        /// properties they stand for, that can be in another schema.
+        const INCLUDE_ALIASES = 0x0800u32;
+    }
+}
+
+/// The form of an array.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArrayForm {
+    /// Unordered array, rdf:Bag.
+    Bag,
+    /// Ordered array, rdf:Seq.
+    Seq,
+    /// Alternate array, rdf:Alt.
+    Alt,
+    /// Alternate array of localized text.
+    AltText,
+}
+
+impl ArrayForm {
+    /// Return the array form from property flags, or `None`
+    /// if the flags aren't for an array.
+    pub fn from_flags(flags: PropFlags) -> Option<ArrayForm> {
+        if flags.contains(PropFlags::ARRAY_IS_ALTTEXT) {
+            Some(ArrayForm::AltText)
+        } else if flags.contains(PropFlags::ARRAY_IS_ALT) {
+            Some(ArrayForm::Alt)
+        } else if flags.contains(PropFlags::ARRAY_IS_ORDERED) {
+            Some(ArrayForm::Seq)
+        } else if flags.contains(PropFlags::VALUE_IS_ARRAY) {
+            Some(ArrayForm::Bag)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<ArrayForm> for PropFlags {
+    /// The flags to create an array of that form.
+    fn from(form: ArrayForm) -> PropFlags {
+        match form {
+            ArrayForm::Bag => PropFlags::VALUE_IS_ARRAY,
+            ArrayForm::Seq => PropFlags::VALUE_IS_ARRAY | PropFlags::ARRAY_IS_ORDERED,
+            ArrayForm::Alt => {
+                PropFlags::VALUE_IS_ARRAY | PropFlags::ARRAY_IS_ORDERED | PropFlags::ARRAY_IS_ALT
+            }
+            ArrayForm::AltText => {
+                PropFlags::VALUE_IS_ARRAY
+                    | PropFlags::ARRAY_IS_ORDERED
+                    | PropFlags::ARRAY_IS_ALT
+                    | PropFlags::ARRAY_IS_ALTTEXT
+            }
+        }
+    }
+}
+
+/// Index to use to address the last item of an array.
+pub const ARRAY_LAST_ITEM: i32 = -1;
+
+/// The default language of alt-text arrays.
+const X_DEFAULT: &str = "x-default";
+
+/// Where to insert an array item relative to the index.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InsertPosition {
+    /// Insert before the item at index.
+    Before,
+    /// Insert after the item at index.
+    After,
+}
+
+impl From<InsertPosition> for PropFlags {
+    fn from(position: InsertPosition) -> PropFlags {
+        match position {
+            InsertPosition::Before => PropFlags::ARRAY_INSERT_BEFORE,
+            InsertPosition::After => PropFlags::ARRAY_INSERT_AFTER,
+        }
+    }
+}
+
 /// The XMP Packet
 pub struct Xmp(*mut c::Xmp);
 
@@ -195,184 +287,92 @@ impl Xmp {
         Err(crate::get_error())
     }
 
+    /// Get the property value as `T`.
+    ///
+    /// ```no_run
+    /// use exempi2::Xmp;
+    ///
+    /// let xmp = Xmp::new();
+    /// let rating = xmp.get::<i32>("http://ns.adobe.com/xap/1.0/", "Rating");
+    /// ```
+    pub fn get<T: FromXmpValue>(&self, schema: &str, name: impl ToXmpPath) -> Result<Option<T>> {
+        T::from_xmp(self, schema, &name.to_xmp_path()?)
+            .map(|property| property.map(|property| property.value))
+    }
+
+    /// Set the property value from `value`.
+    pub fn set<T: ToXmpValue + ?Sized>(
+        &mut self,
+        schema: &str,
+        name: impl ToXmpPath,
+        value: &T,
+    ) -> Result<()> {
+        value.to_xmp(self, schema, &name.to_xmp_path()?, PropFlags::NONE)
+    }
+
     /// Get property as a XmpString.
-    pub fn get_property(
+    pub fn get_property<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
-        propsbits: &mut PropFlags,
-    ) -> Result<XmpString> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        let mut raw_propsbits = 0u32;
-        let mut property = XmpString::new();
-        let result = unsafe {
-            c::xmp_get_property(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                property.as_mut_ptr(),
-                &mut raw_propsbits,
-            )
-        };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
-        if result {
-            Ok(property)
-        } else {
-            Err(crate::get_error())
-        }
+        name: N,
+    ) -> Result<Option<Property<XmpString>>> {
+        XmpString::from_xmp(self, schema, &name.to_xmp_path()?)
     }
 
     /// Get DateTime property.
-    pub fn get_property_date(
+    pub fn get_property_date<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
-        propsbits: &mut PropFlags,
-    ) -> Result<DateTime> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        let mut raw_propsbits = 0u32;
-        let mut property = DateTime::new();
-        let result = unsafe {
-            c::xmp_get_property_date(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                property.as_mut_ptr(),
-                &mut raw_propsbits,
-            )
-        };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
-        if result {
-            Ok(property)
-        } else {
-            Err(crate::get_error())
-        }
+        name: N,
+    ) -> Result<Option<Property<DateTime>>> {
+        DateTime::from_xmp(self, schema, &name.to_xmp_path()?)
     }
 
     /// Get float property
-    pub fn get_property_float(
+    pub fn get_property_float<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
-        propsbits: &mut PropFlags,
-    ) -> Result<f64> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        let mut raw_propsbits = 0u32;
-        let mut property = 0f64;
-        let result = unsafe {
-            c::xmp_get_property_float(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                &mut property as *mut f64,
-                &mut raw_propsbits,
-            )
-        };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
-        if result {
-            Ok(property)
-        } else {
-            Err(crate::get_error())
-        }
+        name: N,
+    ) -> Result<Option<Property<f64>>> {
+        f64::from_xmp(self, schema, &name.to_xmp_path()?)
     }
 
     /// Get bool property
-    pub fn get_property_bool(
+    pub fn get_property_bool<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
-        propsbits: &mut PropFlags,
-    ) -> Result<bool> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        let mut raw_propsbits = 0u32;
-        let mut property = false;
-        let result = unsafe {
-            c::xmp_get_property_bool(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                &mut property as *mut bool,
-                &mut raw_propsbits,
-            )
-        };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
-        if result {
-            Ok(property)
-        } else {
-            Err(crate::get_error())
-        }
+        name: N,
+    ) -> Result<Option<Property<bool>>> {
+        bool::from_xmp(self, schema, &name.to_xmp_path()?)
     }
 
     /// Get i32 property
-    pub fn get_property_i32(
+    pub fn get_property_i32<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
-        propsbits: &mut PropFlags,
-    ) -> Result<i32> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        let mut raw_propsbits = 0u32;
-        let mut property = 0i32;
-        let result = unsafe {
-            c::xmp_get_property_int32(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                &mut property as *mut i32,
-                &mut raw_propsbits,
-            )
-        };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
-        if result {
-            Ok(property)
-        } else {
-            Err(crate::get_error())
-        }
+        name: N,
+    ) -> Result<Option<Property<i32>>> {
+        i32::from_xmp(self, schema, &name.to_xmp_path()?)
     }
 
     /// Get i64 property
-    pub fn get_property_i64(
+    pub fn get_property_i64<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
-        propsbits: &mut PropFlags,
-    ) -> Result<i64> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        let mut raw_propsbits = 0u32;
-        let mut property = 0i64;
-        let result = unsafe {
-            c::xmp_get_property_int64(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                &mut property as *mut i64,
-                &mut raw_propsbits,
-            )
-        };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
-        if result {
-            Ok(property)
-        } else {
-            Err(crate::get_error())
-        }
+        name: N,
+    ) -> Result<Option<Property<i64>>> {
+        i64::from_xmp(self, schema, &name.to_xmp_path()?)
     }
 
     /// Get array item property
-    pub fn get_array_item(
+    pub fn get_array_item<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
+        name: N,
         index: i32,
-        propsbits: &mut PropFlags,
-    ) -> Result<XmpString> {
+    ) -> Result<Option<Property<XmpString>>> {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = CString::new(name.to_xmp_path()?).unwrap();
         let mut raw_propsbits = 0u32;
         let mut property = XmpString::new();
         let result = unsafe {
@@ -385,176 +385,91 @@ impl Xmp {
                 &mut raw_propsbits,
             )
         };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
         if result {
-            Ok(property)
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some(Property::new(property, flags)))
         } else {
-            Err(crate::get_error())
+            crate::absent_or_error()
         }
     }
 
     /// Set a string property value
-    pub fn set_property(
+    pub fn set_property<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         value: &str,
         optionbits: PropFlags,
     ) -> Result<()> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        let s_value = CString::new(value).unwrap();
-        if unsafe {
-            c::xmp_set_property(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                s_value.as_ptr(),
-                optionbits.bits(),
-            )
-        } {
-            Ok(())
-        } else {
-            Err(crate::get_error())
-        }
+        value.to_xmp(self, schema, &name.to_xmp_path()?, optionbits)
     }
 
     /// Set a date property value
-    pub fn set_property_date(
+    pub fn set_property_date<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         value: &DateTime,
         optionbits: PropFlags,
     ) -> Result<()> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        if unsafe {
-            c::xmp_set_property_date(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                value.as_ptr(),
-                optionbits.bits(),
-            )
-        } {
-            Ok(())
-        } else {
-            Err(crate::get_error())
-        }
+        value.to_xmp(self, schema, &name.to_xmp_path()?, optionbits)
     }
 
     /// Set a float property value
-    pub fn set_property_float(
+    pub fn set_property_float<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         value: f64,
         optionbits: PropFlags,
     ) -> Result<()> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        if unsafe {
-            c::xmp_set_property_float(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                value,
-                optionbits.bits(),
-            )
-        } {
-            Ok(())
-        } else {
-            Err(crate::get_error())
-        }
+        value.to_xmp(self, schema, &name.to_xmp_path()?, optionbits)
     }
 
     /// Set a bool property value
-    pub fn set_property_bool(
+    pub fn set_property_bool<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         value: bool,
         optionbits: PropFlags,
     ) -> Result<()> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        if unsafe {
-            c::xmp_set_property_bool(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                value,
-                optionbits.bits(),
-            )
-        } {
-            Ok(())
-        } else {
-            Err(crate::get_error())
-        }
+        value.to_xmp(self, schema, &name.to_xmp_path()?, optionbits)
     }
 
     /// Set an i32 property value
-    pub fn set_property_i32(
+    pub fn set_property_i32<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         value: i32,
         optionbits: PropFlags,
     ) -> Result<()> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        if unsafe {
-            c::xmp_set_property_int32(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                value,
-                optionbits.bits(),
-            )
-        } {
-            Ok(())
-        } else {
-            Err(crate::get_error())
-        }
+        value.to_xmp(self, schema, &name.to_xmp_path()?, optionbits)
     }
 
     /// Set an i64 property value
-    pub fn set_property_i64(
+    pub fn set_property_i64<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         value: i64,
         optionbits: PropFlags,
     ) -> Result<()> {
-        let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
-        if unsafe {
-            c::xmp_set_property_int64(
-                self.0,
-                s_schema.as_ptr(),
-                s_name.as_ptr(),
-                value,
-                optionbits.bits(),
-            )
-        } {
-            Ok(())
-        } else {
-            Err(crate::get_error())
-        }
+        value.to_xmp(self, schema, &name.to_xmp_path()?, optionbits)
     }
 
     /// Set an array item
-    pub fn set_array_item(
+    pub fn set_array_item<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         index: i32,
         value: &str,
         item_options: PropFlags,
     ) -> Result<()> {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = CString::new(name.to_xmp_path()?).unwrap();
         let s_value = CString::new(value).unwrap();
         if unsafe {
             c::xmp_set_array_item(
@@ -572,18 +487,19 @@ impl Xmp {
         }
     }
 
-    // XXX figure out the array options...
     /// Append an array item.
-    pub fn append_array_item(
+    /// `array_options` are used to create the array if it doesn't
+    /// exist. They can be obtained from an `ArrayForm`.
+    pub fn append_array_item<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         array_options: PropFlags,
         value: &str,
         item_options: PropFlags,
     ) -> Result<()> {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = CString::new(name.to_xmp_path()?).unwrap();
         let s_value = CString::new(value).unwrap();
         if unsafe {
             c::xmp_append_array_item(
@@ -601,10 +517,182 @@ impl Xmp {
         }
     }
 
+    /// Get all the items of an array as `T`. Return an empty `Vec` if the
+    /// array doesn't exist, and `BadValue` if an item can't be read, so
+    /// that the indexes aren't shifted.
+    pub fn get_array<T: FromXmpValue>(&self, schema: &str, name: impl ToXmpPath) -> Result<Vec<T>> {
+        let name = name.to_xmp_path()?;
+        let count = self.count_array_items(schema, &name)?;
+        (1..=count as i32)
+            .map(|index| {
+                self.get(schema, XmpPath::new(&name).index(index))?
+                    .ok_or_else(|| Error::from(c::XmpError::BadValue))
+            })
+            .collect()
+    }
+
+    /// Replace the array with `items`, creating an array of `form`.
+    /// The change is atomic: on error the packet is left untouched.
+    /// Alt-text arrays need languages, use `set_localized_text()`
+    /// instead. `BadParam` is returned for `ArrayForm::AltText`.
+    pub fn set_array<T: ToXmpValue>(
+        &mut self,
+        schema: &str,
+        name: impl ToXmpPath,
+        form: ArrayForm,
+        items: &[T],
+    ) -> Result<()> {
+        if form == ArrayForm::AltText {
+            return Err(Error::from(c::XmpError::BadParam));
+        }
+        let name = name.to_xmp_path()?;
+        let mut xmp = self.clone();
+        if xmp.has_property(schema, &name) {
+            xmp.delete_property(schema, &name)?;
+        }
+        if items.is_empty() {
+            xmp.set_property(schema, &name, "", PropFlags::from(form))?;
+        }
+        for item in items {
+            // Append a placeholder that is then set to the typed value.
+            xmp.append_array_item(schema, &name, PropFlags::from(form), "", PropFlags::NONE)?;
+            xmp.set(schema, XmpPath::new(&name).last(), item)?;
+        }
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+
+    /// Count the items of an array. Return 0 if the array doesn't exist,
+    /// `BadXPath` if the property isn't an array.
+    pub fn count_array_items<N: ToXmpPath>(&self, schema: &str, name: N) -> Result<usize> {
+        let name = name.to_xmp_path()?;
+        match self.get_property(schema, &name)? {
+            None => return Ok(0),
+            Some(property) if !property.is_array() => {
+                return Err(Error::from(c::XmpError::BadXPath))
+            }
+            _ => {}
+        }
+        // There is no API to count, so we probe the items.
+        let mut count = 0;
+        while self.has_property(schema, XmpPath::new(&name).index(count as i32 + 1)) {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Insert an array item before or after the item at index.
+    pub fn insert_array_item<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        name: N,
+        index: i32,
+        position: InsertPosition,
+        value: &str,
+        item_options: PropFlags,
+    ) -> Result<()> {
+        self.set_array_item(
+            schema,
+            name,
+            index,
+            value,
+            item_options | PropFlags::from(position),
+        )
+    }
+
+    /// Delete the array item at index.
+    /// `ARRAY_LAST_ITEM` will delete the last item.
+    pub fn delete_array_item<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        name: N,
+        index: i32,
+    ) -> Result<()> {
+        let path = XmpPath::new(&name.to_xmp_path()?);
+        let path = if index == ARRAY_LAST_ITEM {
+            path.last()
+        } else {
+            path.index(index)
+        };
+        self.delete_property(schema, &path)
+    }
+
+    /// Move the array item at index `from` so that it ends up at index `to`.
+    /// `ARRAY_LAST_ITEM` can be used for either.
+    /// The qualifiers of the item are moved along. Only simple items
+    /// can be moved, `BadParam` is returned for structs and arrays.
+    pub fn move_array_item<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        name: N,
+        from: i32,
+        to: i32,
+    ) -> Result<()> {
+        let name = name.to_xmp_path()?;
+        let count = self.count_array_items(schema, &name)? as i32;
+        let from = if from == ARRAY_LAST_ITEM { count } else { from };
+        let to = if to == ARRAY_LAST_ITEM { count } else { to };
+        if from < 1 || from > count || to < 1 || to > count {
+            return Err(Error::from(c::XmpError::BadIndex));
+        }
+        if from == to {
+            return Ok(());
+        }
+
+        let item = self
+            .get_array_item(schema, &name, from)?
+            .ok_or_else(|| Error::from(c::XmpError::BadIndex))?;
+        if item.flags.intersects(PropFlags::COMPOSITE_MASK) {
+            return Err(Error::from(c::XmpError::BadParam));
+        }
+        let item_path = XmpPath::new(&name).index(from).compose()?;
+        let qualifiers: Vec<(String, XmpString)> =
+            XmpIterator::with_path(self, schema, &item_path, IterFlags::PROPERTIES)
+                .filter(|v| v.option.contains(PropFlags::IS_QUALIFIER))
+                .filter_map(|v| {
+                    String::from(&v.name)
+                        .rsplit_once("/?")
+                        .filter(|(parent, _)| !parent.contains("/?"))
+                        .map(|(_, qual)| (qual.to_owned(), v.value))
+                })
+                .collect();
+
+        self.delete_array_item(schema, &name, from)?;
+        if to < count {
+            self.insert_array_item(
+                schema,
+                &name,
+                to,
+                InsertPosition::Before,
+                &String::from(&item.value),
+                item.flags & PropFlags::VALUE_IS_URI,
+            )?;
+        } else {
+            self.insert_array_item(
+                schema,
+                &name,
+                count - 1,
+                InsertPosition::After,
+                &String::from(&item.value),
+                item.flags & PropFlags::VALUE_IS_URI,
+            )?;
+        }
+        let item_path = XmpPath::new(&name).index(to).compose()?;
+        for (qual, value) in qualifiers {
+            self.set_property(
+                schema,
+                format!("{}/?{}", item_path, qual),
+                &String::from(&value),
+                PropFlags::NONE,
+            )?;
+        }
+        Ok(())
+    }
+
     /// Delete a property
-    pub fn delete_property(&mut self, schema: &str, name: &str) -> Result<()> {
+    pub fn delete_property<N: ToXmpPath>(&mut self, schema: &str, name: N) -> Result<()> {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = CString::new(name.to_xmp_path()?).unwrap();
         if unsafe { c::xmp_delete_property(self.0, s_schema.as_ptr(), s_name.as_ptr()) } {
             Ok(())
         } else {
@@ -613,23 +701,438 @@ impl Xmp {
     }
 
     /// Has a property
-    pub fn has_property(&self, schema: &str, name: &str) -> bool {
+    pub fn has_property<N: ToXmpPath>(&self, schema: &str, name: N) -> bool {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = match name.to_xmp_path() {
+            Ok(name) => CString::new(name).unwrap(),
+            Err(_) => return false,
+        };
         unsafe { c::xmp_has_property(self.0, s_schema.as_ptr(), s_name.as_ptr()) }
     }
 
+    /// Remove all the properties of the schema `uri`.
+    /// The change is atomic: on error the packet is left untouched.
+    ///
+    /// ```no_run
+    /// use exempi2::{RemoveFlags, Xmp};
+    ///
+    /// let mut xmp = Xmp::new();
+    /// // Strip the camera raw settings before publishing.
+    /// let crs = "http://ns.adobe.com/camera-raw-settings/1.0/";
+    /// xmp.remove_schema(crs, RemoveFlags::INCLUDE_INTERNAL).unwrap();
+    /// ```
+    pub fn remove_schema(&mut self, uri: &str, options: RemoveFlags) -> Result<()> {
+        let mut xmp = self.clone();
+        xmp.remove_schema_properties(uri, options)?;
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+
+    /// Remove the properties of all the schemas except the ones in
+    /// `uris`. The change is atomic: on error the packet is left untouched.
+    pub fn retain_schemas(&mut self, uris: &[&str], options: RemoveFlags) -> Result<()> {
+        let schemas: Vec<String> = XmpIterator::with_path(
+            self,
+            "",
+            "",
+            IterFlags::PROPERTIES | IterFlags::JUST_CHILDREN,
+        )
+        .map(|v| String::from(&v.schema))
+        .filter(|schema| !uris.contains(&schema.as_str()))
+        .collect();
+        let mut xmp = self.clone();
+        for schema in schemas {
+            xmp.remove_schema_properties(&schema, options)?;
+        }
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+
+    fn remove_schema_properties(&mut self, uri: &str, options: RemoveFlags) -> Result<()> {
+        let mut iter_flags = IterFlags::PROPERTIES | IterFlags::JUST_CHILDREN;
+        if options.contains(RemoveFlags::INCLUDE_ALIASES) {
+            iter_flags |= IterFlags::INCLUDE_ALIASES;
+        }
+        // Collect first as the properties can't be deleted while iterating.
+        let names: Vec<String> = XmpIterator::with_path(self, uri, "", iter_flags)
+            .filter(|v| {
+                options.contains(RemoveFlags::INCLUDE_INTERNAL)
+                    || !v.option.contains(PropFlags::IS_INTERNAL)
+            })
+            .map(|v| String::from(&v.name))
+            .filter(|name| !name.is_empty())
+            .collect();
+        for name in names {
+            self.delete_property(uri, &name)?;
+        }
+        Ok(())
+    }
+
+    /// Get a struct field as a XmpString.
+    pub fn get_struct_field<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+    ) -> Result<Option<Property<XmpString>>> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.get_property(schema, &path)
+    }
+
+    /// Get a struct field as a date.
+    pub fn get_struct_field_date<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+    ) -> Result<Option<Property<DateTime>>> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.get_property_date(schema, &path)
+    }
+
+    /// Get a struct field as a float.
+    pub fn get_struct_field_float<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+    ) -> Result<Option<Property<f64>>> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.get_property_float(schema, &path)
+    }
+
+    /// Get a struct field as a bool.
+    pub fn get_struct_field_bool<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+    ) -> Result<Option<Property<bool>>> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.get_property_bool(schema, &path)
+    }
+
+    /// Get a struct field as an i32.
+    pub fn get_struct_field_i32<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+    ) -> Result<Option<Property<i32>>> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.get_property_i32(schema, &path)
+    }
+
+    /// Get a struct field as an i64.
+    pub fn get_struct_field_i64<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+    ) -> Result<Option<Property<i64>>> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.get_property_i64(schema, &path)
+    }
+
+    /// Set a struct field to a string value.
+    pub fn set_struct_field<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+        value: &str,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.set_property(schema, &path, value, optionbits)
+    }
+
+    /// Set a struct field to a date value.
+    pub fn set_struct_field_date<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+        value: &DateTime,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.set_property_date(schema, &path, value, optionbits)
+    }
+
+    /// Set a struct field to a float value.
+    pub fn set_struct_field_float<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+        value: f64,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.set_property_float(schema, &path, value, optionbits)
+    }
+
+    /// Set a struct field to a bool value.
+    pub fn set_struct_field_bool<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+        value: bool,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.set_property_bool(schema, &path, value, optionbits)
+    }
+
+    /// Set a struct field to an i32 value.
+    pub fn set_struct_field_i32<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+        value: i32,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.set_property_i32(schema, &path, value, optionbits)
+    }
+
+    /// Set a struct field to an i64 value.
+    pub fn set_struct_field_i64<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+        value: i64,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.set_property_i64(schema, &path, value, optionbits)
+    }
+
+    /// Delete a struct field.
+    pub fn delete_struct_field<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        struct_name: N,
+        field_ns: &str,
+        field_name: &str,
+    ) -> Result<()> {
+        let path = XmpPath::new(&struct_name.to_xmp_path()?).field(field_ns, field_name);
+        self.delete_property(schema, &path)
+    }
+
+    /// Get a property qualifier as a XmpString.
+    pub fn get_qualifier<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+    ) -> Result<Option<Property<XmpString>>> {
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.get_property(schema, &path)
+    }
+
+    /// Get a property qualifier as a date.
+    pub fn get_qualifier_date<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+    ) -> Result<Option<Property<DateTime>>> {
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.get_property_date(schema, &path)
+    }
+
+    /// Get a property qualifier as a float.
+    pub fn get_qualifier_float<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+    ) -> Result<Option<Property<f64>>> {
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.get_property_float(schema, &path)
+    }
+
+    /// Get a property qualifier as a bool.
+    pub fn get_qualifier_bool<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+    ) -> Result<Option<Property<bool>>> {
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.get_property_bool(schema, &path)
+    }
+
+    /// Get a property qualifier as an i32.
+    pub fn get_qualifier_i32<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+    ) -> Result<Option<Property<i32>>> {
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.get_property_i32(schema, &path)
+    }
+
+    /// Get a property qualifier as an i64.
+    pub fn get_qualifier_i64<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+    ) -> Result<Option<Property<i64>>> {
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.get_property_i64(schema, &path)
+    }
+
+    /// Set a property qualifier to a string value.
+    pub fn set_qualifier<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+        value: &str,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        if !self.has_property(schema, &prop_name) {
+            return Err(Error::from(c::XmpError::BadXPath));
+        }
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.set_property(schema, &path, value, optionbits)
+    }
+
+    /// Set a property qualifier to a date value.
+    pub fn set_qualifier_date<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+        value: &DateTime,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        if !self.has_property(schema, &prop_name) {
+            return Err(Error::from(c::XmpError::BadXPath));
+        }
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.set_property_date(schema, &path, value, optionbits)
+    }
+
+    /// Set a property qualifier to a float value.
+    pub fn set_qualifier_float<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+        value: f64,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        if !self.has_property(schema, &prop_name) {
+            return Err(Error::from(c::XmpError::BadXPath));
+        }
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.set_property_float(schema, &path, value, optionbits)
+    }
+
+    /// Set a property qualifier to a bool value.
+    pub fn set_qualifier_bool<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+        value: bool,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        if !self.has_property(schema, &prop_name) {
+            return Err(Error::from(c::XmpError::BadXPath));
+        }
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.set_property_bool(schema, &path, value, optionbits)
+    }
+
+    /// Set a property qualifier to an i32 value.
+    pub fn set_qualifier_i32<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+        value: i32,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        if !self.has_property(schema, &prop_name) {
+            return Err(Error::from(c::XmpError::BadXPath));
+        }
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.set_property_i32(schema, &path, value, optionbits)
+    }
+
+    /// Set a property qualifier to an i64 value.
+    pub fn set_qualifier_i64<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+        value: i64,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        if !self.has_property(schema, &prop_name) {
+            return Err(Error::from(c::XmpError::BadXPath));
+        }
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.set_property_i64(schema, &path, value, optionbits)
+    }
+
+    /// Delete a property qualifier.
+    pub fn delete_qualifier<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        prop_name: N,
+        qual_ns: &str,
+        qual_name: &str,
+    ) -> Result<()> {
+        let path = XmpPath::new(&prop_name.to_xmp_path()?).qualifier(qual_ns, qual_name);
+        self.delete_property(schema, &path)
+    }
+
     /// Get localized text.
-    pub fn get_localized_text(
+    /// Return the actual language of the item, and the item.
+    pub fn get_localized_text<N: ToXmpPath>(
         &self,
         schema: &str,
-        name: &str,
+        name: N,
         gen_lang: &str,
         spec_lang: &str,
-        propsbits: &mut PropFlags,
-    ) -> Result<(XmpString, XmpString)> {
+    ) -> Result<Option<(XmpString, Property<XmpString>)>> {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = CString::new(name.to_xmp_path()?).unwrap();
         let s_gen_lang = CString::new(gen_lang).unwrap();
         let s_spec_lang = CString::new(spec_lang).unwrap();
 
@@ -649,26 +1152,26 @@ impl Xmp {
                 &mut raw_propsbits,
             )
         };
-        *propsbits = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
         if result {
-            Ok((actual_lang, value))
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some((actual_lang, Property::new(value, flags))))
         } else {
-            Err(crate::get_error())
+            crate::absent_or_error()
         }
     }
 
     /// Set localized text.
-    pub fn set_localized_text(
+    pub fn set_localized_text<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         gen_lang: &str,
         spec_lang: &str,
         value: &str,
         propbits: PropFlags,
     ) -> Result<()> {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = CString::new(name.to_xmp_path()?).unwrap();
         let s_gen_lang = CString::new(gen_lang).unwrap();
         let s_spec_lang = CString::new(spec_lang).unwrap();
         let s_value = CString::new(value).unwrap();
@@ -689,16 +1192,114 @@ impl Xmp {
         }
     }
 
+    /// Get all the items of an alt-text array, as `(lang, value)`.
+    /// Return an empty `Vec` if the property doesn't exist, `BadXPath`
+    /// if it isn't an alt-text array.
+    pub fn get_alt_text_map<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        name: N,
+    ) -> Result<Vec<(String, String)>> {
+        let name = name.to_xmp_path()?;
+        match self.get_property(schema, &name)? {
+            None => return Ok(vec![]),
+            Some(property) if property.array_form() != Some(ArrayForm::AltText) => {
+                return Err(Error::from(c::XmpError::BadXPath))
+            }
+            _ => {}
+        }
+        let count = self.count_array_items(schema, &name)?;
+        (1..=count as i32)
+            .map(|index| {
+                let item = XmpPath::new(&name).index(index);
+                let lang = self.get_qualifier(schema, &item, NS_XML_URI, "lang")?;
+                let value = self.get_property(schema, &item)?;
+                // A malformed item without xml:lang get an empty language.
+                let to_string = |p: Option<Property<XmpString>>| {
+                    p.map(|p| String::from(&p.value)).unwrap_or_default()
+                };
+                Ok((to_string(lang), to_string(value)))
+            })
+            .collect()
+    }
+
+    /// Replace the alt-text array with the `(lang, value)` entries.
+    /// The `x-default` entry, if any, is put first.
+    /// The change is atomic: on error the packet is left untouched.
+    pub fn set_alt_text_map<N: ToXmpPath, L: AsRef<str>, V: AsRef<str>>(
+        &mut self,
+        schema: &str,
+        name: N,
+        entries: &[(L, V)],
+    ) -> Result<()> {
+        let name = name.to_xmp_path()?;
+        let is_default = |lang: &str| lang.eq_ignore_ascii_case(X_DEFAULT);
+        let entries = entries
+            .iter()
+            .filter(|(lang, _)| is_default(lang.as_ref()))
+            .chain(
+                entries
+                    .iter()
+                    .filter(|(lang, _)| !is_default(lang.as_ref())),
+            );
+
+        let mut xmp = self.clone();
+        if xmp.has_property(schema, &name) {
+            xmp.delete_property(schema, &name)?;
+        }
+        xmp.set_property(schema, &name, "", PropFlags::from(ArrayForm::AltText))?;
+        for (lang, value) in entries {
+            xmp.append_array_item(
+                schema,
+                &name,
+                PropFlags::from(ArrayForm::AltText),
+                value.as_ref(),
+                PropFlags::NONE,
+            )?;
+            xmp.set_qualifier(
+                schema,
+                XmpPath::new(&name).last(),
+                NS_XML_URI,
+                "lang",
+                lang.as_ref(),
+                PropFlags::NONE,
+            )?;
+        }
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+
+    /// Promote the language `lang` of the alt-text array to `x-default`:
+    /// the `x-default` item get the value for `lang`.
+    /// Return `BadParam` if there is no item for `lang`.
+    pub fn promote_alt_text_language<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        name: N,
+        lang: &str,
+    ) -> Result<()> {
+        let name = name.to_xmp_path()?;
+        let mut entries = self.get_alt_text_map(schema, &name)?;
+        let value = entries
+            .iter()
+            .find(|(l, _)| l.eq_ignore_ascii_case(lang))
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| Error::from(c::XmpError::BadParam))?;
+        entries.retain(|(l, _)| !l.eq_ignore_ascii_case(X_DEFAULT));
+        entries.insert(0, (X_DEFAULT.to_owned(), value));
+        self.set_alt_text_map(schema, &name, &entries)
+    }
+
     /// Delete localize text.
-    pub fn delete_localized_text(
+    pub fn delete_localized_text<N: ToXmpPath>(
         &mut self,
         schema: &str,
-        name: &str,
+        name: N,
         gen_lang: &str,
         spec_lang: &str,
     ) -> Result<()> {
         let s_schema = CString::new(schema).unwrap();
-        let s_name = CString::new(name).unwrap();
+        let s_name = CString::new(name.to_xmp_path()?).unwrap();
         let s_gen_lang = CString::new(gen_lang).unwrap();
         let s_spec_lang = CString::new(spec_lang).unwrap();
         if unsafe {
@@ -0,0 +1,286 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use crate::xmp::{ArrayForm, SerialFlags, Xmp};
+use crate::xmpstring::XmpString;
+use crate::xmptree::{XmpNode, XmpNodeKind, XmpTree};
+use crate::Result;
+use crate::NS_XML_URI;
+
+/// Compare the lists of nodes, item by item.
+fn cmp_node_lists(a: &[XmpNode], b: &[XmpNode]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| cmp_nodes(a, b))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+/// Total order of the nodes, to sort the bag items.
+fn cmp_nodes(a: &XmpNode, b: &XmpNode) -> Ordering {
+    (&a.ns, &a.name, &a.value, a.flags.bits())
+        .cmp(&(&b.ns, &b.name, &b.value, b.flags.bits()))
+        .then_with(|| cmp_node_lists(a.children(), b.children()))
+        .then_with(|| cmp_node_lists(&a.qualifiers, &b.qualifiers))
+}
+
+/// Sort the nodes by namespace and name.
+fn sort_named(nodes: &mut [XmpNode]) {
+    nodes.sort_by(|a, b| (&a.ns, &a.name).cmp(&(&b.ns, &b.name)));
+}
+
+/// Put the node in canonical order, recursively.
+fn canonicalize_node(node: &mut XmpNode) {
+    node.qualifiers.iter_mut().for_each(canonicalize_node);
+    sort_named(&mut node.qualifiers);
+    match node.kind {
+        XmpNodeKind::Simple => {}
+        XmpNodeKind::Struct(ref mut fields) => {
+            fields.iter_mut().for_each(canonicalize_node);
+            sort_named(fields);
+        }
+        XmpNodeKind::Array(form, ref mut items) => {
+            items.iter_mut().for_each(canonicalize_node);
+            match form {
+                ArrayForm::Bag => items.sort_by(cmp_nodes),
+                // x-default first, then by language.
+                ArrayForm::AltText => items.sort_by_cached_key(|item| {
+                    let lang = item
+                        .qualifier(NS_XML_URI, "lang")
+                        .map(|lang| lang.value.clone())
+                        .unwrap_or_default();
+                    (lang != "x-default", lang)
+                }),
+                ArrayForm::Seq | ArrayForm::Alt => {}
+            }
+        }
+    }
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, the result doesn't change
+/// across Rust releases.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn hash_node_list(hasher: &mut Fnv1a, nodes: &[XmpNode]) {
+    hasher.write_u64(nodes.len() as u64);
+    for node in nodes {
+        hash_node(hasher, node);
+    }
+}
+
+fn hash_node(hasher: &mut Fnv1a, node: &XmpNode) {
+    hasher.write_str(&node.ns);
+    hasher.write_str(&node.name);
+    hasher.write_str(&node.value);
+    hasher.write_u64(u64::from(node.flags.bits()));
+    hash_node_list(hasher, node.children());
+    hash_node_list(hasher, &node.qualifiers);
+}
+
+impl XmpTree {
+    /// Put the tree in canonical order: the schemas are sorted by uri,
+    /// the properties, struct fields and qualifiers by namespace and
+    /// name, and the bag items by value. Alt-text items are sorted by
+    /// language with `x-default` first. Other arrays keep their order.
+    ///
+    /// Two trees of semantically equal packets are equal once
+    /// canonicalized.
+    pub fn canonicalize(&mut self) {
+        self.schemas.sort_by(|a, b| a.uri.cmp(&b.uri));
+        for schema in &mut self.schemas {
+            schema.properties.iter_mut().for_each(canonicalize_node);
+            sort_named(&mut schema.properties);
+        }
+    }
+
+    /// Return a hash of the content, independent of the order of the
+    /// schemas, properties, fields, qualifiers and bag items. The prefixes
+    /// are ignored. The value is stable across runs, platforms and
+    /// versions of Rust.
+    pub fn content_hash(&self) -> u64 {
+        let mut tree = self.clone();
+        tree.canonicalize();
+
+        let mut hasher = Fnv1a::new();
+        hasher.write_u64(tree.schemas.len() as u64);
+        for schema in &tree.schemas {
+            hasher.write_str(&schema.uri);
+            hash_node_list(&mut hasher, &schema.properties);
+        }
+        hasher.finish()
+    }
+}
+
+impl Xmp {
+    /// Return a hash of the content of the packet, stable across runs.
+    /// Semantically equal packets have the same hash. See
+    /// `XmpTree::content_hash()`.
+    ///
+    /// ```no_run
+    /// use std::collections::HashMap;
+    /// use exempi2::Xmp;
+    ///
+    /// let mut cache: HashMap<u64, Xmp> = HashMap::new();
+    /// let xmp = Xmp::new();
+    /// cache.entry(xmp.content_hash()).or_insert(xmp);
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        self.to_tree().content_hash()
+    }
+
+    /// Return a copy of the packet with the properties in canonical
+    /// order, as with `XmpTree::canonicalize()`. The prefixes are the
+    /// registered ones.
+    ///
+    /// The XMP Toolkit serializes in the order of the properties, so
+    /// serializing the copy with the same options give the same bytes
+    /// for semantically equal packets. The `rdf:about` isn't kept.
+    pub fn canonical(&self) -> Result<Xmp> {
+        let mut tree = self.to_tree();
+        tree.canonicalize();
+        Xmp::from_tree(&tree)
+    }
+
+    /// Serialize the packet in canonical order. See `Xmp::canonical()`.
+    ///
+    /// ```no_run
+    /// use exempi2::{SerialFlags, Xmp};
+    ///
+    /// let xmp = Xmp::new();
+    /// let sidecar = xmp.serialize_canonical(SerialFlags::OMITPACKETWRAPPER, 0);
+    /// ```
+    pub fn serialize_canonical(&self, options: SerialFlags, padding: u32) -> Result<XmpString> {
+        self.canonical()?.serialize(options, padding)
+    }
+
+    /// Serialize the packet in canonical order with some formatting
+    /// options. See `Xmp::canonical()`.
+    pub fn serialize_canonical_and_format(
+        &self,
+        options: SerialFlags,
+        padding: u32,
+        newline: &str,
+        tab: &str,
+        indent: i32,
+    ) -> Result<XmpString> {
+        self.canonical()?
+            .serialize_and_format(options, padding, newline, tab, indent)
+    }
+}
+
+/// Semantic equality: the order of the schemas, properties, struct
+/// fields, qualifiers and bag items doesn't matter, nor does the
+/// serialization the packets were parsed from.
+impl PartialEq for Xmp {
+    fn eq(&self, other: &Xmp) -> bool {
+        let mut tree = self.to_tree();
+        let mut other = other.to_tree();
+        tree.canonicalize();
+        other.canonicalize();
+        tree.schemas.len() == other.schemas.len()
+            && tree.schemas.iter().zip(&other.schemas).all(|(a, b)| {
+                // The prefix isn't part of the content.
+                a.uri == b.uri && a.properties == b.properties
+            })
+    }
+}
+
+impl Eq for Xmp {}
+
+/// Consistent with `PartialEq`: feed the `content_hash()`.
+impl Hash for Xmp {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.content_hash());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::xmp::{ArrayForm, PropFlags};
+    use crate::xmptree::fixture::{array, schema, simple, NS};
+    use crate::xmptree::{XmpNode, XmpTree};
+
+    const NS2: &str = "http://rust.figuiere.net/ns/rust2/";
+
+    #[test]
+    fn test_canonicalize() {
+        let other = || XmpNode::new(NS2, "other", "1", PropFlags::NONE);
+        let mut a = XmpTree {
+            schemas: vec![
+                schema(NS2, "rust2", vec![other()]),
+                schema(
+                    NS,
+                    "rust",
+                    vec![
+                        simple("test", "foo"),
+                        array("bag", ArrayForm::Bag, &["b", "c", "a"]),
+                    ],
+                ),
+            ],
+        };
+        let b = XmpTree {
+            schemas: vec![
+                schema(
+                    NS,
+                    "rust",
+                    vec![
+                        array("bag", ArrayForm::Bag, &["a", "b", "c"]),
+                        simple("test", "foo"),
+                    ],
+                ),
+                schema(NS2, "rust2", vec![other()]),
+            ],
+        };
+        assert_ne!(a, b);
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        a.canonicalize();
+        assert_eq!(a, b);
+
+        let c = XmpTree {
+            schemas: vec![schema(
+                NS,
+                "rust",
+                vec![array("bag", ArrayForm::Bag, &["a", "b", "d"])],
+            )],
+        };
+        assert_ne!(a.content_hash(), c.content_hash());
+        // The hash is stable.
+        assert_eq!(c.content_hash(), 9299119296069279969);
+    }
+}
@@ -0,0 +1,349 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::fmt;
+
+use crate::xmp::{ArrayForm, Xmp};
+use crate::xmptree::{XmpNode, XmpNodeKind, XmpTree};
+use crate::NS_XML_URI;
+
+/// A change between two packets, as returned by `Xmp::diff()`.
+///
+/// The path is a path expression relative to the schema, like
+/// `dc:subject[2]`, `exif:Flash/exif:Fired` or `dc:title[?xml:lang="fr"]`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum XmpChange {
+    /// The node was added.
+    Added {
+        /// The schema uri.
+        schema: String,
+        /// The path of the node.
+        path: String,
+        /// The node added.
+        node: XmpNode,
+    },
+    /// The node was removed.
+    Removed {
+        /// The schema uri.
+        schema: String,
+        /// The path of the node.
+        path: String,
+        /// The node removed.
+        node: XmpNode,
+    },
+    /// The value of a simple node was modified.
+    Modified {
+        /// The schema uri.
+        schema: String,
+        /// The path of the node.
+        path: String,
+        /// The old value.
+        old: String,
+        /// The new value.
+        new: String,
+    },
+}
+
+impl XmpChange {
+    /// The schema uri of the change.
+    pub fn schema(&self) -> &str {
+        match *self {
+            XmpChange::Added { ref schema, .. }
+            | XmpChange::Removed { ref schema, .. }
+            | XmpChange::Modified { ref schema, .. } => schema,
+        }
+    }
+
+    /// The path of the change.
+    pub fn path(&self) -> &str {
+        match *self {
+            XmpChange::Added { ref path, .. }
+            | XmpChange::Removed { ref path, .. }
+            | XmpChange::Modified { ref path, .. } => path,
+        }
+    }
+}
+
+/// Write a node change, with the value if it is simple.
+fn write_node_change(
+    f: &mut fmt::Formatter,
+    sign: char,
+    path: &str,
+    node: &XmpNode,
+) -> fmt::Result {
+    match node.kind {
+        XmpNodeKind::Simple => write!(f, "{} {} = \"{}\"", sign, path, node.value),
+        _ => write!(f, "{} {}", sign, path),
+    }
+}
+
+impl fmt::Display for XmpChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XmpChange::Added {
+                ref path, ref node, ..
+            } => write_node_change(f, '+', path, node),
+            XmpChange::Removed {
+                ref path, ref node, ..
+            } => write_node_change(f, '-', path, node),
+            XmpChange::Modified {
+                ref path,
+                ref old,
+                ref new,
+                ..
+            } => write!(f, "~ {} = \"{}\" -> \"{}\"", path, old, new),
+        }
+    }
+}
+
+/// Compute the changes between two trees.
+struct Differ<'a> {
+    old: &'a XmpTree,
+    new: &'a XmpTree,
+    schema: &'a str,
+    changes: Vec<XmpChange>,
+}
+
+impl<'a> Differ<'a> {
+    /// Return `prefix:name`. The prefixes of the schemas in the trees
+    /// are used first.
+    fn qualified_name(&self, ns: &str, name: &str) -> String {
+        match self.old.schema(ns) {
+            Some(_) => self.old.qualified_name(ns, name),
+            None => self.new.qualified_name(ns, name),
+        }
+    }
+
+    fn added(&mut self, path: String, node: &XmpNode) {
+        self.changes.push(XmpChange::Added {
+            schema: self.schema.to_owned(),
+            path,
+            node: node.clone(),
+        });
+    }
+
+    fn removed(&mut self, path: String, node: &XmpNode) {
+        self.changes.push(XmpChange::Removed {
+            schema: self.schema.to_owned(),
+            path,
+            node: node.clone(),
+        });
+    }
+
+    /// Diff the named nodes: properties, fields or qualifiers.
+    /// `sep` is the separator put after the parent path.
+    fn diff_named(&mut self, parent: &str, sep: &str, old: &[XmpNode], new: &[XmpNode]) {
+        for old_node in old {
+            let path = format!(
+                "{}{}{}",
+                parent,
+                sep,
+                self.qualified_name(&old_node.ns, &old_node.name)
+            );
+            match new
+                .iter()
+                .find(|n| n.ns == old_node.ns && n.name == old_node.name)
+            {
+                Some(new_node) => self.diff_node(&path, old_node, new_node),
+                None => self.removed(path, old_node),
+            }
+        }
+        for new_node in new
+            .iter()
+            .filter(|n| !old.iter().any(|o| o.ns == n.ns && o.name == n.name))
+        {
+            let path = format!(
+                "{}{}{}",
+                parent,
+                sep,
+                self.qualified_name(&new_node.ns, &new_node.name)
+            );
+            self.added(path, new_node);
+        }
+    }
+
+    /// Diff the array items in order.
+    fn diff_ordered(&mut self, path: &str, old: &[XmpNode], new: &[XmpNode]) {
+        for index in 0..old.len().max(new.len()) {
+            let item_path = format!("{}[{}]", path, index + 1);
+            match (old.get(index), new.get(index)) {
+                (Some(old_item), Some(new_item)) => self.diff_node(&item_path, old_item, new_item),
+                (Some(old_item), None) => self.removed(item_path, old_item),
+                (None, Some(new_item)) => self.added(item_path, new_item),
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// Diff the array items as sets. The paths are the index in each
+    /// array.
+    fn diff_unordered(&mut self, path: &str, old: &[XmpNode], new: &[XmpNode]) {
+        let mut unmatched: Vec<Option<&XmpNode>> = new.iter().map(Some).collect();
+        for (index, old_item) in old.iter().enumerate() {
+            match unmatched.iter_mut().find(|n| *n == &Some(old_item)) {
+                Some(matched) => *matched = None,
+                None => self.removed(format!("{}[{}]", path, index + 1), old_item),
+            }
+        }
+        for (index, new_item) in unmatched.iter().enumerate() {
+            if let Some(new_item) = new_item {
+                self.added(format!("{}[{}]", path, index + 1), new_item);
+            }
+        }
+    }
+
+    /// Diff the alt-text items by language.
+    fn diff_alt_text(&mut self, path: &str, old: &[XmpNode], new: &[XmpNode]) {
+        let lang = |item: &XmpNode| {
+            item.qualifier(NS_XML_URI, "lang")
+                .map(|lang| lang.value.clone())
+                .unwrap_or_default()
+        };
+        let item_path = |lang: &str| format!("{}[?xml:lang=\"{}\"]", path, lang);
+        for old_item in old {
+            let old_lang = lang(old_item);
+            match new.iter().find(|n| lang(n) == old_lang) {
+                Some(new_item) => self.diff_node(&item_path(&old_lang), old_item, new_item),
+                None => self.removed(item_path(&old_lang), old_item),
+            }
+        }
+        for new_item in new {
+            let new_lang = lang(new_item);
+            if !old.iter().any(|o| lang(o) == new_lang) {
+                self.added(item_path(&new_lang), new_item);
+            }
+        }
+    }
+
+    /// Diff two nodes at the same path.
+    fn diff_node(&mut self, path: &str, old: &XmpNode, new: &XmpNode) {
+        match (&old.kind, &new.kind) {
+            (XmpNodeKind::Simple, XmpNodeKind::Simple) => {
+                if old.value != new.value {
+                    self.changes.push(XmpChange::Modified {
+                        schema: self.schema.to_owned(),
+                        path: path.to_owned(),
+                        old: old.value.clone(),
+                        new: new.value.clone(),
+                    });
+                }
+            }
+            (XmpNodeKind::Struct(old_fields), XmpNodeKind::Struct(new_fields)) => {
+                self.diff_named(path, "/", old_fields, new_fields)
+            }
+            (XmpNodeKind::Array(old_form, old_items), XmpNodeKind::Array(new_form, new_items))
+                if old_form == new_form =>
+            {
+                match old_form {
+                    ArrayForm::Bag => self.diff_unordered(path, old_items, new_items),
+                    ArrayForm::Seq | ArrayForm::Alt => {
+                        self.diff_ordered(path, old_items, new_items)
+                    }
+                    ArrayForm::AltText => self.diff_alt_text(path, old_items, new_items),
+                }
+            }
+            _ => {
+                // Different kind of node: replaced.
+                self.removed(path.to_owned(), old);
+                self.added(path.to_owned(), new);
+                return;
+            }
+        }
+        self.diff_named(path, "/?", &old.qualifiers, &new.qualifiers);
+    }
+}
+
+impl XmpTree {
+    /// Return the changes from `self` to `other`.
+    pub fn diff(&self, other: &XmpTree) -> Vec<XmpChange> {
+        let mut differ = Differ {
+            old: self,
+            new: other,
+            schema: "",
+            changes: vec![],
+        };
+        let no_properties = vec![];
+        let schemas = self.schemas.iter().chain(
+            other
+                .schemas
+                .iter()
+                .filter(|schema| self.schema(&schema.uri).is_none()),
+        );
+        for schema in schemas {
+            differ.schema = &schema.uri;
+            let old = self
+                .schema(&schema.uri)
+                .map_or(&no_properties, |schema| &schema.properties);
+            let new = other
+                .schema(&schema.uri)
+                .map_or(&no_properties, |schema| &schema.properties);
+            differ.diff_named("", "", old, new);
+        }
+        differ.changes
+    }
+}
+
+impl Xmp {
+    /// Return the changes from `self` to `other`: the added, removed
+    /// and modified properties, struct fields, array items and qualifiers.
+    ///
+    /// Bag arrays are compared as sets, other arrays are compared in
+    /// order, and alt-text arrays by language.
+    ///
+    /// ```no_run
+    /// use exempi2::Xmp;
+    ///
+    /// let on_disk = Xmp::new();
+    /// let edited = Xmp::new();
+    /// for change in on_disk.diff(&edited) {
+    ///     println!("{}", change);
+    /// }
+    /// ```
+    pub fn diff(&self, other: &Xmp) -> Vec<XmpChange> {
+        self.to_tree().diff(&other.to_tree())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::XmpChange;
+    use crate::xmp::ArrayForm;
+    use crate::xmptree::fixture::{array, simple, tree, NS};
+
+    #[test]
+    fn test_diff() {
+        let old = tree(vec![
+            simple("test", "foo"),
+            simple("gone", "bar"),
+            array("bag", ArrayForm::Bag, &["a", "b", "c"]),
+            array("seq", ArrayForm::Seq, &["a", "b"]),
+        ]);
+        let new = tree(vec![
+            simple("test", "foobar"),
+            array("bag", ArrayForm::Bag, &["c", "a", "d"]),
+            array("seq", ArrayForm::Seq, &["b", "a", "c"]),
+        ]);
+        assert!(old.diff(&old).is_empty());
+
+        let changes = old.diff(&new);
+        let paths: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "~ rust:test = \"foo\" -> \"foobar\"",
+                "- rust:gone = \"bar\"",
+                "- rust:bag[2] = \"b\"",
+                "+ rust:bag[3] = \"d\"",
+                "~ rust:seq[1] = \"a\" -> \"b\"",
+                "~ rust:seq[2] = \"b\" -> \"a\"",
+                "+ rust:seq[3] = \"c\"",
+            ]
+        );
+        assert!(changes.iter().all(|c| c.schema() == NS));
+        assert!(matches!(changes[1], XmpChange::Removed { .. }));
+    }
+}
@@ -0,0 +1,128 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::fmt;
+
+use crate::xmp::Xmp;
+use crate::xmptree::{XmpNode, XmpNodeKind, XmpTree};
+
+/// Write `node` and its children, indented by `depth`.
+/// With the alternate flag the raw flags bits are written too.
+fn write_node(
+    f: &mut fmt::Formatter,
+    tree: &XmpTree,
+    depth: usize,
+    label: &str,
+    node: &XmpNode,
+) -> fmt::Result {
+    write!(f, "{:indent$}{}", "", label, indent = depth * 2)?;
+    match node.kind {
+        XmpNodeKind::Simple => write!(f, " = \"{}\"", node.value)?,
+        XmpNodeKind::Struct(_) => write!(f, " (Struct)")?,
+        XmpNodeKind::Array(form, _) => write!(f, " ({:?})", form)?,
+    }
+    let names: Vec<&str> = node.flags.iter_names().map(|(name, _)| name).collect();
+    if !names.is_empty() {
+        write!(f, " [{}]", names.join(" | "))?;
+    }
+    if f.alternate() {
+        write!(f, " (0x{:08x})", node.flags.bits())?;
+    }
+    writeln!(f)?;
+
+    for qualifier in &node.qualifiers {
+        let label = format!("?{}", tree.qualified_name(&qualifier.ns, &qualifier.name));
+        write_node(f, tree, depth + 1, &label, qualifier)?;
+    }
+    match node.kind {
+        XmpNodeKind::Simple => {}
+        XmpNodeKind::Struct(ref fields) => {
+            for field in fields {
+                let label = tree.qualified_name(&field.ns, &field.name);
+                write_node(f, tree, depth + 1, &label, field)?;
+            }
+        }
+        XmpNodeKind::Array(_, ref items) => {
+            for (index, item) in items.iter().enumerate() {
+                write_node(f, tree, depth + 1, &format!("[{}]", index + 1), item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Dump the tree, one node per line, like the XMP Toolkit
+/// `DumpObject()`. Use `{:#}` to add the raw flags bits.
+impl fmt::Display for XmpTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for schema in &self.schemas {
+            writeln!(f, "{} ({}:)", schema.uri, schema.prefix)?;
+            for property in &schema.properties {
+                let label = format!("{}:{}", schema.prefix, property.name);
+                write_node(f, self, 1, &label, property)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Dump the properties, one per line, with the values, the array
+/// forms, the qualifiers and the flags names. Use `{:#}` to add the
+/// raw flags bits.
+///
+/// ```no_run
+/// use exempi2::Xmp;
+///
+/// let xmp = Xmp::new();
+/// println!("{}", xmp);
+/// // http://purl.org/dc/elements/1.1/ (dc:)
+/// //   dc:title (AltText) [VALUE_IS_ARRAY | ARRAY_IS_ORDERED | ARRAY_IS_ALT | ARRAY_IS_ALTTEXT]
+/// //     [1] = "Hello" [HAS_QUALIFIERS | HAS_LANG]
+/// //       ?xml:lang = "x-default" [IS_QUALIFIER]
+/// ```
+impl fmt::Display for Xmp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_tree(), f)
+    }
+}
+
+/// Same as `Display`, so that the packet can be used in `assert_eq!()`
+/// and logs. `{:#?}` adds the raw flags bits.
+impl fmt::Debug for Xmp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_tree(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::xmp::ArrayForm;
+    use crate::xmptree::fixture::{array, qualified, qualifier, simple, tree};
+    use crate::xmptree::XmpNodeKind;
+
+    #[test]
+    fn test_dump() {
+        let mut bag = array("bag", ArrayForm::Bag, &["a"]);
+        if let XmpNodeKind::Array(_, ref mut items) = bag.kind {
+            items[0] = qualified(items[0].clone(), vec![qualifier("qual", "q")]);
+        }
+        let tree = tree(vec![simple("test", "foo"), bag]);
+
+        assert_eq!(
+            tree.to_string(),
+            "http://rust.figuiere.net/ns/rust/ (rust:)\n\
+             \x20 rust:test = \"foo\"\n\
+             \x20 rust:bag (Bag) [VALUE_IS_ARRAY]\n\
+             \x20   [1] = \"a\" [HAS_QUALIFIERS]\n\
+             \x20     ?rust:qual = \"q\" [IS_QUALIFIER]\n"
+        );
+        assert_eq!(
+            format!("{:#}", tree).lines().nth(2),
+            Some("  rust:bag (Bag) [VALUE_IS_ARRAY] (0x00000200)")
+        );
+    }
+}
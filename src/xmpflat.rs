@@ -0,0 +1,597 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmptree::{XmpNode, XmpNodeKind, XmpSchema, XmpTree};
+use crate::Result;
+use crate::NS_XML_URI;
+
+/// A step of a flat key, after the property name. The array indexes
+/// are ordered as numbers.
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Step {
+    /// Struct field: namespace uri and name.
+    Field(String, String),
+    /// Array item, 1-based.
+    Index(usize),
+    /// Alt-text item for the language.
+    Lang(String),
+    /// Qualifier: namespace uri and name.
+    Qualifier(String, String),
+}
+
+/// Split `prefix:name` and resolve the prefix.
+fn resolve_name(
+    qname: &str,
+    resolve: &impl Fn(&str) -> Result<String>,
+) -> Result<(String, String)> {
+    let (prefix, name) = qname
+        .split_once(':')
+        .ok_or_else(|| Error::from(c::XmpError::BadXPath))?;
+    if name.is_empty() {
+        return Err(Error::from(c::XmpError::BadXPath));
+    }
+    Ok((resolve(prefix)?, name.to_owned()))
+}
+
+/// Parse a flat key into the namespace and name of the property and
+/// the steps. `resolve` return the namespace for a prefix.
+fn parse_key(
+    key: &str,
+    resolve: &impl Fn(&str) -> Result<String>,
+) -> Result<(String, String, Vec<Step>)> {
+    let bad_path = || Error::from(c::XmpError::BadXPath);
+    let end = key.find(['/', '[']).unwrap_or(key.len());
+    let (ns, name) = resolve_name(&key[..end], resolve)?;
+    let mut steps = vec![];
+    let mut rest = &key[end..];
+    while !rest.is_empty() {
+        if let Some(selector) = rest.strip_prefix('[') {
+            let close = selector.find(']').ok_or_else(bad_path)?;
+            let selector_end = close + 2;
+            let selector = &selector[..close];
+            if let Some(lang) = selector.strip_prefix("?xml:lang=") {
+                let lang = lang.trim_matches('"');
+                if lang.is_empty() {
+                    return Err(bad_path());
+                }
+                steps.push(Step::Lang(lang.to_owned()));
+            } else {
+                let index = selector.parse().map_err(|_| bad_path())?;
+                if index == 0 {
+                    return Err(Error::from(c::XmpError::BadIndex));
+                }
+                steps.push(Step::Index(index));
+            }
+            rest = &rest[selector_end..];
+        } else {
+            let next = rest.strip_prefix('/').ok_or_else(bad_path)?;
+            let end = next.find(['/', '[']).unwrap_or(next.len());
+            let step = &next[..end];
+            match step.strip_prefix('?') {
+                Some(qname) => {
+                    let (ns, name) = resolve_name(qname, resolve)?;
+                    steps.push(Step::Qualifier(ns, name));
+                }
+                None => {
+                    let (ns, name) = resolve_name(step, resolve)?;
+                    steps.push(Step::Field(ns, name));
+                }
+            }
+            rest = &next[end..];
+        }
+    }
+    Ok((ns, name, steps))
+}
+
+/// Make a node created empty a struct or an array. Return `BadXPath`
+/// if it is already something else.
+fn make_composite(node: &mut XmpNode, kind: XmpNodeKind, flags: PropFlags) -> Result<()> {
+    let compatible = match (&node.kind, &kind) {
+        (XmpNodeKind::Simple, _) => node.value.is_empty(),
+        (XmpNodeKind::Struct(_), XmpNodeKind::Struct(_)) => true,
+        (XmpNodeKind::Array(form, _), XmpNodeKind::Array(new_form, _)) => form == new_form,
+        _ => false,
+    };
+    if !compatible {
+        return Err(Error::from(c::XmpError::BadXPath));
+    }
+    if node.kind == XmpNodeKind::Simple {
+        node.kind = kind;
+        node.flags = (node.flags & !PropFlags::VALUE_IS_URI) | flags;
+    }
+    Ok(())
+}
+
+/// Return the child of `node` for the step, creating it if needed.
+fn child<'a>(node: &'a mut XmpNode, step: &Step) -> Result<&'a mut XmpNode> {
+    match step {
+        Step::Qualifier(ns, name) => {
+            node.flags |= PropFlags::HAS_QUALIFIERS;
+            let qualifiers = &mut node.qualifiers;
+            let pos = match qualifiers
+                .iter()
+                .position(|q| &q.ns == ns && &q.name == name)
+            {
+                Some(pos) => pos,
+                None => {
+                    qualifiers.push(XmpNode::new(ns, name, "", PropFlags::IS_QUALIFIER));
+                    qualifiers.len() - 1
+                }
+            };
+            Ok(&mut qualifiers[pos])
+        }
+        Step::Field(ns, name) => {
+            make_composite(
+                node,
+                XmpNodeKind::Struct(vec![]),
+                PropFlags::VALUE_IS_STRUCT,
+            )?;
+            match node.kind {
+                XmpNodeKind::Struct(ref mut fields) => {
+                    let pos = match fields.iter().position(|f| &f.ns == ns && &f.name == name) {
+                        Some(pos) => pos,
+                        None => {
+                            fields.push(XmpNode::new(ns, name, "", PropFlags::NONE));
+                            fields.len() - 1
+                        }
+                    };
+                    Ok(&mut fields[pos])
+                }
+                _ => Err(Error::from(c::XmpError::BadXPath)),
+            }
+        }
+        Step::Index(index) => {
+            // Keep the form of an existing array.
+            let form = node.array_form().unwrap_or(ArrayForm::Bag);
+            make_composite(
+                node,
+                XmpNodeKind::Array(form, vec![]),
+                PropFlags::from(form),
+            )?;
+            match node.kind {
+                XmpNodeKind::Array(_, ref mut items) => {
+                    // The keys are sorted, so the item is either there
+                    // or the next one.
+                    if *index > items.len() + 1 {
+                        return Err(Error::from(c::XmpError::BadIndex));
+                    }
+                    if *index > items.len() {
+                        items.push(XmpNode::new("", "", "", PropFlags::NONE));
+                    }
+                    Ok(&mut items[index - 1])
+                }
+                _ => Err(Error::from(c::XmpError::BadXPath)),
+            }
+        }
+        Step::Lang(lang) => {
+            make_composite(
+                node,
+                XmpNodeKind::Array(ArrayForm::AltText, vec![]),
+                PropFlags::from(ArrayForm::AltText),
+            )?;
+            match node.kind {
+                XmpNodeKind::Array(_, ref mut items) => {
+                    let found = items.iter().position(|item| {
+                        item.qualifier(NS_XML_URI, "lang")
+                            .map(|q| &q.value == lang)
+                            .unwrap_or(false)
+                    });
+                    let pos = match found {
+                        Some(pos) => pos,
+                        None => {
+                            let mut item = XmpNode::new(
+                                "",
+                                "",
+                                "",
+                                PropFlags::HAS_QUALIFIERS | PropFlags::HAS_LANG,
+                            );
+                            item.qualifiers.push(XmpNode::new(
+                                NS_XML_URI,
+                                "lang",
+                                lang,
+                                PropFlags::IS_QUALIFIER,
+                            ));
+                            // x-default is always the first item.
+                            let pos = if lang == "x-default" { 0 } else { items.len() };
+                            items.insert(pos, item);
+                            pos
+                        }
+                    };
+                    Ok(&mut items[pos])
+                }
+                _ => Err(Error::from(c::XmpError::BadXPath)),
+            }
+        }
+    }
+}
+
+/// Return the existing child of `node` for the step.
+fn find_child<'a>(node: &'a mut XmpNode, step: &Step) -> Option<&'a mut XmpNode> {
+    match (step, &mut node.kind) {
+        (Step::Qualifier(ns, name), _) => node
+            .qualifiers
+            .iter_mut()
+            .find(|q| &q.ns == ns && &q.name == name),
+        (Step::Field(ns, name), XmpNodeKind::Struct(fields)) => {
+            fields.iter_mut().find(|f| &f.ns == ns && &f.name == name)
+        }
+        (Step::Index(index), XmpNodeKind::Array(_, items)) => items.get_mut(index - 1),
+        (Step::Lang(lang), XmpNodeKind::Array(_, items)) => items.iter_mut().find(|item| {
+            item.qualifier(NS_XML_URI, "lang")
+                .map(|q| &q.value == lang)
+                .unwrap_or(false)
+        }),
+        _ => None,
+    }
+}
+
+/// Add the leaves of `node` to the map, and the form of its arrays to
+/// `forms`, with `path` as the key prefix. `lang_in_path` is set for
+/// the alt-text items, whose xml:lang qualifier is the selector in the
+/// path.
+fn flatten(
+    map: &mut BTreeMap<String, String>,
+    forms: &mut BTreeMap<String, ArrayForm>,
+    tree: &XmpTree,
+    path: &str,
+    node: &XmpNode,
+    lang_in_path: bool,
+) {
+    match node.kind {
+        XmpNodeKind::Simple => {
+            map.insert(path.to_owned(), node.value.clone());
+        }
+        XmpNodeKind::Struct(ref fields) => {
+            for field in fields {
+                let path = format!("{}/{}", path, tree.qualified_name(&field.ns, &field.name));
+                flatten(map, forms, tree, &path, field, false);
+            }
+        }
+        XmpNodeKind::Array(form, ref items) => {
+            forms.insert(path.to_owned(), form);
+            for (index, item) in items.iter().enumerate() {
+                let lang = item
+                    .qualifier(NS_XML_URI, "lang")
+                    .filter(|_| form == ArrayForm::AltText);
+                let path = match lang {
+                    Some(lang) => format!("{}[?xml:lang=\"{}\"]", path, lang.value),
+                    None => format!("{}[{}]", path, index + 1),
+                };
+                flatten(map, forms, tree, &path, item, lang.is_some());
+            }
+        }
+    }
+    for qualifier in &node.qualifiers {
+        if lang_in_path && qualifier.ns == NS_XML_URI && qualifier.name == "lang" {
+            continue;
+        }
+        let path = format!(
+            "{}/?{}",
+            path,
+            tree.qualified_name(&qualifier.ns, &qualifier.name)
+        );
+        flatten(map, forms, tree, &path, qualifier, false);
+    }
+}
+
+impl XmpTree {
+    /// Return the flat view of the tree. See `Xmp::to_flat_map()`.
+    pub fn to_flat_map(&self) -> BTreeMap<String, String> {
+        self.to_flat_map_with_forms().0
+    }
+
+    /// Return the flat view of the tree and the array forms. See
+    /// `Xmp::to_flat_map_with_forms()`.
+    pub fn to_flat_map_with_forms(
+        &self,
+    ) -> (BTreeMap<String, String>, BTreeMap<String, ArrayForm>) {
+        let mut map = BTreeMap::new();
+        let mut forms = BTreeMap::new();
+        for schema in &self.schemas {
+            for property in &schema.properties {
+                let path = self.qualified_name(&schema.uri, &property.name);
+                flatten(&mut map, &mut forms, self, &path, property, false);
+            }
+        }
+        (map, forms)
+    }
+
+    /// Build the tree from a flat view and the array forms, with
+    /// `resolve` returning the namespace for a prefix.
+    fn from_flat_map_with(
+        map: &BTreeMap<String, String>,
+        forms: &BTreeMap<String, ArrayForm>,
+        resolve: impl Fn(&str) -> Result<String>,
+    ) -> Result<XmpTree> {
+        let mut entries = map
+            .iter()
+            .map(|(key, value)| {
+                let (ns, name, steps) = parse_key(key, &resolve)?;
+                Ok((ns, name, steps, key, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // As strings [10] comes before [2].
+        entries.sort();
+
+        let mut tree = XmpTree::default();
+        for (ns, name, steps, key, value) in entries {
+            let index = match tree.schemas.iter().position(|schema| schema.uri == ns) {
+                Some(index) => index,
+                None => {
+                    let prefix = key.split_once(':').map(|(prefix, _)| prefix);
+                    tree.schemas
+                        .push(XmpSchema::new(&ns, prefix.unwrap_or_default()));
+                    tree.schemas.len() - 1
+                }
+            };
+            let properties = &mut tree.schemas[index].properties;
+            let pos = match properties.iter().position(|p| p.name == name) {
+                Some(pos) => pos,
+                None => {
+                    properties.push(XmpNode::new(&ns, &name, "", PropFlags::NONE));
+                    properties.len() - 1
+                }
+            };
+            let mut node = &mut properties[pos];
+            for step in &steps {
+                node = child(node, step)?;
+            }
+            if node.kind != XmpNodeKind::Simple {
+                return Err(Error::from(c::XmpError::BadXPath));
+            }
+            node.value = value.clone();
+        }
+
+        for (path, form) in forms {
+            let (ns, name, steps) = parse_key(path, &resolve)?;
+            let mut node = tree
+                .schemas
+                .iter_mut()
+                .find(|schema| schema.uri == ns)
+                .and_then(|schema| schema.properties.iter_mut().find(|p| p.name == name));
+            for step in &steps {
+                node = node.and_then(|node| find_child(node, step));
+            }
+            // The arrays without a value aren't in the tree.
+            let node = match node {
+                Some(node) => node,
+                None => continue,
+            };
+            match node.kind {
+                // The alt-text arrays are the ones with the xml:lang
+                // selectors.
+                XmpNodeKind::Array(ref mut array_form, _)
+                    if (*array_form == ArrayForm::AltText) == (*form == ArrayForm::AltText) =>
+                {
+                    *array_form = *form;
+                    node.flags =
+                        (node.flags & !PropFlags::ARRAY_FORM_MASK) | PropFlags::from(*form);
+                }
+                _ => return Err(Error::from(c::XmpError::BadXPath)),
+            }
+        }
+        Ok(tree)
+    }
+}
+
+impl Xmp {
+    /// Return a flat view of the packet: the path of each simple value,
+    /// with the registered prefixes, and the value. Like
+    /// `dc:creator[2]`, `exif:Flash/exif:Fired`,
+    /// `dc:title[?xml:lang="x-default"]` or `dc:rights/?xmp:note`.
+    ///
+    /// Empty arrays and structs have no value, so they aren't in the map.
+    ///
+    /// ```no_run
+    /// use exempi2::Xmp;
+    ///
+    /// let xmp = Xmp::new();
+    /// for (path, value) in xmp.to_flat_map() {
+    ///     println!("{}\t{}", path, value);
+    /// }
+    /// ```
+    pub fn to_flat_map(&self) -> BTreeMap<String, String> {
+        self.to_tree().to_flat_map()
+    }
+
+    /// Return the flat view of the packet, like `Xmp::to_flat_map()`,
+    /// and the form of each array by path, like `dc:creator` for
+    /// `ArrayForm::Seq`. `Xmp::from_flat_map_with_forms()` rebuilds the
+    /// packet from both.
+    pub fn to_flat_map_with_forms(
+        &self,
+    ) -> (BTreeMap<String, String>, BTreeMap<String, ArrayForm>) {
+        self.to_tree().to_flat_map_with_forms()
+    }
+
+    /// Build a packet from a flat view like the one of
+    /// `Xmp::to_flat_map()`. The arrays and the structs are rebuilt
+    /// from the keys. Arrays with numeric indexes are bags, alt-text
+    /// arrays are made from the `xml:lang` selectors, quoted or not.
+    /// Use `Xmp::from_flat_map_with_forms()` to keep the ordered and
+    /// the alternate arrays.
+    ///
+    /// The prefixes must be registered, or `BadSchema` is returned.
+    /// `BadXPath` is returned for a key that can't be parsed, or if
+    /// the keys don't agree on the kind of a node. `BadIndex` is
+    /// returned if the array indexes have a gap.
+    pub fn from_flat_map(map: &BTreeMap<String, String>) -> Result<Xmp> {
+        Xmp::from_flat_map_with_forms(map, &BTreeMap::new())
+    }
+
+    /// Build a packet from a flat view, like `Xmp::from_flat_map()`,
+    /// with the form of the arrays from `forms`, by path. The arrays
+    /// without a form are bags. The forms of arrays that aren't in the
+    /// flat view are ignored.
+    ///
+    /// `BadXPath` is returned if a path of `forms` isn't an array, or
+    /// if the form is `AltText` for an array with numeric indexes, or
+    /// the other way around.
+    ///
+    /// ```no_run
+    /// use exempi2::Xmp;
+    ///
+    /// let xmp = Xmp::new();
+    /// let (map, forms) = xmp.to_flat_map_with_forms();
+    /// let copy = Xmp::from_flat_map_with_forms(&map, &forms).unwrap();
+    /// ```
+    pub fn from_flat_map_with_forms(
+        map: &BTreeMap<String, String>,
+        forms: &BTreeMap<String, ArrayForm>,
+    ) -> Result<Xmp> {
+        let tree = XmpTree::from_flat_map_with(map, forms, |prefix| {
+            crate::prefix_namespace(prefix)
+                .map(|ns| String::from(&ns))
+                .map_err(|_| Error::from(c::XmpError::BadSchema))
+        })?;
+        Xmp::from_tree(&tree)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{parse_key, Step};
+    use crate::error::Error;
+    use crate::xmp::{ArrayForm, PropFlags};
+    use crate::xmptree::fixture::{array, tree, NS};
+    use crate::xmptree::{XmpNodeKind, XmpTree};
+    use crate::Result;
+
+    fn resolve(prefix: &str) -> Result<String> {
+        match prefix {
+            "rust" => Ok(NS.to_owned()),
+            "xml" => Ok(crate::NS_XML_URI.to_owned()),
+            _ => Err(Error::from(c::XmpError::BadSchema)),
+        }
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(
+            parse_key("rust:test", &resolve),
+            Ok((NS.to_owned(), "test".to_owned(), vec![]))
+        );
+        assert_eq!(
+            parse_key("rust:a/rust:b[2]/?rust:q", &resolve),
+            Ok((
+                NS.to_owned(),
+                "a".to_owned(),
+                vec![
+                    Step::Field(NS.to_owned(), "b".to_owned()),
+                    Step::Index(2),
+                    Step::Qualifier(NS.to_owned(), "q".to_owned()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_key("rust:title[?xml:lang=x-default]", &resolve),
+            parse_key("rust:title[?xml:lang=\"x-default\"]", &resolve)
+        );
+        assert!(parse_key("test", &resolve).is_err());
+        assert!(parse_key("rust:a[0]", &resolve).is_err());
+        assert!(parse_key("rust:a[1", &resolve).is_err());
+        assert_eq!(
+            parse_key("rust:a[1]é/rust:b", &resolve),
+            Err(Error::from(c::XmpError::BadXPath))
+        );
+        assert_eq!(
+            parse_key("dc:title", &resolve),
+            Err(Error::from(c::XmpError::BadSchema))
+        );
+    }
+
+    #[test]
+    fn test_flat_map_forms() {
+        let seq = tree(vec![array("seq", ArrayForm::Seq, &["b", "a"])]);
+        let (map, forms) = seq.to_flat_map_with_forms();
+        assert_eq!(forms.get("rust:seq"), Some(&ArrayForm::Seq));
+        assert_eq!(XmpTree::from_flat_map_with(&map, &forms, resolve), Ok(seq));
+    }
+
+    #[test]
+    fn test_flat_map() {
+        let mut map = BTreeMap::new();
+        for (key, value) in [
+            ("rust:test", "foo"),
+            ("rust:bag[1]", "a"),
+            ("rust:bag[2]", "b"),
+            ("rust:bag[3]", "c"),
+            ("rust:bag[4]", "d"),
+            ("rust:bag[5]", "e"),
+            ("rust:bag[6]", "f"),
+            ("rust:bag[7]", "g"),
+            ("rust:bag[8]", "h"),
+            ("rust:bag[9]", "i"),
+            ("rust:bag[10]", "j"),
+            ("rust:title[?xml:lang=\"fr\"]", "Bonjour"),
+            ("rust:title[?xml:lang=\"x-default\"]", "Hello"),
+            ("rust:location/rust:city", "Montréal"),
+            ("rust:test/?rust:note", "bar"),
+        ] {
+            map.insert(key.to_owned(), value.to_owned());
+        }
+        let tree = XmpTree::from_flat_map_with(&map, &BTreeMap::new(), resolve).unwrap();
+        let schema = tree.schema(NS).unwrap();
+        assert_eq!(schema.prefix, "rust");
+
+        let bag = schema.property("bag").unwrap();
+        assert_eq!(bag.array_form(), Some(ArrayForm::Bag));
+        assert_eq!(bag.children().len(), 10);
+        assert_eq!(bag.children()[1].value, "b");
+        assert_eq!(bag.children()[9].value, "j");
+        let title = schema.property("title").unwrap();
+        assert_eq!(title.array_form(), Some(ArrayForm::AltText));
+        assert_eq!(title.children()[0].value, "Hello");
+        let location = schema.property("location").unwrap();
+        assert!(matches!(location.kind, XmpNodeKind::Struct(_)));
+        assert_eq!(location.field(NS, "city").unwrap().value, "Montréal");
+        let test = schema.property("test").unwrap();
+        assert_eq!(test.value, "foo");
+        assert_eq!(test.qualifier(NS, "note").unwrap().value, "bar");
+
+        assert_eq!(tree.to_flat_map(), map);
+
+        // A gap in the indexes.
+        let mut sparse = map.clone();
+        sparse.remove("rust:bag[5]");
+        assert_eq!(
+            XmpTree::from_flat_map_with(&sparse, &BTreeMap::new(), resolve),
+            Err(Error::from(c::XmpError::BadIndex))
+        );
+
+        // The array forms.
+        let mut forms = BTreeMap::new();
+        forms.insert("rust:bag".to_owned(), ArrayForm::Seq);
+        forms.insert("rust:title".to_owned(), ArrayForm::AltText);
+        forms.insert("rust:missing".to_owned(), ArrayForm::Alt);
+        let tree = XmpTree::from_flat_map_with(&map, &forms, resolve).unwrap();
+        let bag = tree.schema(NS).unwrap().property("bag").unwrap();
+        assert_eq!(bag.array_form(), Some(ArrayForm::Seq));
+        assert_eq!(bag.flags, PropFlags::from(ArrayForm::Seq));
+        forms.insert("rust:title".to_owned(), ArrayForm::Seq);
+        assert_eq!(
+            XmpTree::from_flat_map_with(&map, &forms, resolve),
+            Err(Error::from(c::XmpError::BadXPath))
+        );
+        forms.remove("rust:title");
+        forms.insert("rust:test".to_owned(), ArrayForm::Seq);
+        assert_eq!(
+            XmpTree::from_flat_map_with(&map, &forms, resolve),
+            Err(Error::from(c::XmpError::BadXPath))
+        );
+
+        // A node can't be both a struct and an array.
+        map.insert("rust:location[1]".to_owned(), "x".to_owned());
+        assert!(XmpTree::from_flat_map_with(&map, &BTreeMap::new(), resolve).is_err());
+    }
+}
@@ -6,7 +6,9 @@
 //
 
 use crate::xmp::{PropFlags, Xmp};
+use crate::xmppath::ToXmpPath;
 use crate::xmpstring::XmpString;
+use crate::Result;
 use std::ffi::CString;
 
 bitflags! {
@@ -60,7 +62,7 @@ bitflags! {
 /// let xmp = Xmp::new();
 ///
 /// let iter = XmpIterator::new(&xmp, "http://ns.adobe.com/exif/1.0/",
-///                             [], IterFlags::PROPERTIES);
+///                             "", IterFlags::PROPERTIES).unwrap();
 ///
 /// iter.for_each(|value| {
 ///     println!("{}:{} = {} / {}", value.schema, value.name, value.value, value.option.bits());
@@ -90,7 +92,27 @@ impl IteratorValue {
 
 impl XmpIterator {
     /// Construct a new `XmpIterator` from a native pointer
-    pub fn new<S, N>(xmp: &Xmp, schema: S, name: N, propsbits: IterFlags) -> XmpIterator
+    /// Return an error if the name path can't be composed.
+    pub fn new<S, N>(xmp: &Xmp, schema: S, name: N, propsbits: IterFlags) -> Result<XmpIterator>
+    where
+        S: AsRef<[u8]>,
+        N: ToXmpPath,
+    {
+        Ok(XmpIterator::with_path(
+            xmp,
+            schema,
+            name.to_xmp_path()?,
+            propsbits,
+        ))
+    }
+
+    /// Construct a new `XmpIterator` for a path already composed.
+    pub(crate) fn with_path<S, N>(
+        xmp: &Xmp,
+        schema: S,
+        name: N,
+        propsbits: IterFlags,
+    ) -> XmpIterator
     where
         S: AsRef<[u8]>,
         N: AsRef<[u8]>,
@@ -132,6 +154,9 @@ impl Iterator for XmpIterator {
     /// Iterate to the next element following the option set by the iterator
     ///
     fn next(&mut self) -> Option<Self::Item> {
+        if self.is_null() {
+            return None;
+        }
         let mut value = IteratorValue::new();
         let mut raw_option: u32 = 0;
         if unsafe {
@@ -173,7 +198,17 @@ fn iterator_works() {
         "http://ns.adobe.com/xap/1.0/",
         "keyword",
         IterFlags::from_bits(0).unwrap_or_default(),
-    );
+    )
+    .unwrap();
 
     assert_eq!(iter.next(), None);
+
+    // A path that can't be composed is an error, not an empty iterator.
+    assert!(XmpIterator::new(
+        &xmp,
+        "http://ns.adobe.com/xap/1.0/",
+        &b"\xff"[..],
+        IterFlags::PROPERTIES,
+    )
+    .is_err());
 }
@@ -0,0 +1,298 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmptree::{register_if_unknown, XmpNode, XmpNodeKind, XmpSchema, XmpTree};
+use crate::Result;
+use crate::{NS_RDF_URI, NS_XML_URI};
+
+fn form_name(form: ArrayForm) -> &'static str {
+    match form {
+        ArrayForm::Bag => "bag",
+        ArrayForm::Seq => "seq",
+        ArrayForm::Alt => "alt",
+        ArrayForm::AltText => "alt-text",
+    }
+}
+
+fn form_from_name(name: &str) -> Result<ArrayForm> {
+    match name {
+        "bag" => Ok(ArrayForm::Bag),
+        "seq" => Ok(ArrayForm::Seq),
+        "alt" => Ok(ArrayForm::Alt),
+        "alt-text" => Ok(ArrayForm::AltText),
+        _ => Err(Error::from(c::XmpError::BadValue)),
+    }
+}
+
+fn nodes_to_json(nodes: &[XmpNode]) -> Value {
+    Value::Array(nodes.iter().map(node_to_json).collect())
+}
+
+fn node_to_json(node: &XmpNode) -> Value {
+    let mut object = Map::new();
+    if !node.ns.is_empty() {
+        object.insert("ns".into(), node.ns.clone().into());
+    }
+    if !node.name.is_empty() {
+        object.insert("name".into(), node.name.clone().into());
+    }
+    match node.kind {
+        XmpNodeKind::Simple => {
+            object.insert("value".into(), node.value.clone().into());
+            if node.flags.contains(PropFlags::VALUE_IS_URI) {
+                object.insert("uri".into(), true.into());
+            }
+        }
+        XmpNodeKind::Struct(ref fields) => {
+            object.insert("fields".into(), nodes_to_json(fields));
+        }
+        XmpNodeKind::Array(form, ref items) => {
+            object.insert("array".into(), form_name(form).into());
+            object.insert("items".into(), nodes_to_json(items));
+        }
+    }
+    if !node.qualifiers.is_empty() {
+        object.insert("qualifiers".into(), nodes_to_json(&node.qualifiers));
+    }
+    Value::Object(object)
+}
+
+/// Return the string for `key`, empty if absent.
+fn json_string(object: &Map<String, Value>, key: &str) -> Result<String> {
+    match object.get(key) {
+        None => Ok(String::new()),
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(_) => Err(Error::from(c::XmpError::BadValue)),
+    }
+}
+
+/// Return the nodes for `key`, empty if absent.
+fn json_nodes(object: &Map<String, Value>, key: &str) -> Result<Vec<XmpNode>> {
+    match object.get(key) {
+        None => Ok(vec![]),
+        Some(Value::Array(nodes)) => nodes.iter().map(node_from_json).collect(),
+        Some(_) => Err(Error::from(c::XmpError::BadValue)),
+    }
+}
+
+/// Build the node. The flags are derived like the XMP Toolkit does.
+fn node_from_json(value: &Value) -> Result<XmpNode> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| Error::from(c::XmpError::BadValue))?;
+    let (kind, mut flags) = if object.contains_key("fields") {
+        (
+            XmpNodeKind::Struct(json_nodes(object, "fields")?),
+            PropFlags::VALUE_IS_STRUCT,
+        )
+    } else if let Some(form) = object.get("array") {
+        let form = form_from_name(form.as_str().unwrap_or_default())?;
+        (
+            XmpNodeKind::Array(form, json_nodes(object, "items")?),
+            PropFlags::from(form),
+        )
+    } else if object.get("uri") == Some(&Value::Bool(true)) {
+        (XmpNodeKind::Simple, PropFlags::VALUE_IS_URI)
+    } else {
+        (XmpNodeKind::Simple, PropFlags::NONE)
+    };
+
+    let mut qualifiers = json_nodes(object, "qualifiers")?;
+    for qualifier in &mut qualifiers {
+        qualifier.flags |= PropFlags::IS_QUALIFIER;
+        if qualifier.ns == NS_XML_URI && qualifier.name == "lang" {
+            flags |= PropFlags::HAS_LANG;
+        } else if qualifier.ns == NS_RDF_URI && qualifier.name == "type" {
+            flags |= PropFlags::HAS_TYPE;
+        }
+    }
+    if !qualifiers.is_empty() {
+        flags |= PropFlags::HAS_QUALIFIERS;
+    }
+
+    Ok(XmpNode {
+        ns: json_string(object, "ns")?,
+        name: json_string(object, "name")?,
+        value: match kind {
+            XmpNodeKind::Simple => json_string(object, "value")?,
+            _ => String::new(),
+        },
+        flags,
+        kind,
+        qualifiers,
+    })
+}
+
+/// Collect the namespaces of the nodes, recursively.
+fn collect_namespaces<'a>(nodes: &'a [XmpNode], namespaces: &mut Vec<&'a str>) {
+    for node in nodes {
+        if !node.ns.is_empty() && !namespaces.contains(&node.ns.as_str()) {
+            namespaces.push(&node.ns);
+        }
+        collect_namespaces(node.children(), namespaces);
+        collect_namespaces(&node.qualifiers, namespaces);
+    }
+}
+
+impl XmpTree {
+    /// Convert the tree to JSON. See `Xmp::to_json()` for the format.
+    pub fn to_json_value(&self) -> Value {
+        let mut uris = vec![];
+        for schema in &self.schemas {
+            collect_namespaces(&schema.properties, &mut uris);
+        }
+        let mut namespaces = Map::new();
+        for uri in uris {
+            if let Some(prefix) = self.prefix_for(uri) {
+                namespaces.insert(prefix, uri.into());
+            }
+        }
+
+        let schemas = self
+            .schemas
+            .iter()
+            .map(|schema| {
+                let mut object = Map::new();
+                object.insert("uri".into(), schema.uri.clone().into());
+                object.insert("prefix".into(), schema.prefix.clone().into());
+                object.insert("properties".into(), nodes_to_json(&schema.properties));
+                Value::Object(object)
+            })
+            .collect();
+
+        let mut object = Map::new();
+        object.insert("namespaces".into(), Value::Object(namespaces));
+        object.insert("schemas".into(), Value::Array(schemas));
+        Value::Object(object)
+    }
+
+    /// Build the tree from JSON. See `Xmp::to_json()` for the format.
+    /// Return `BadValue` if the JSON doesn't follow the format.
+    /// The namespaces aren't registered.
+    pub fn from_json_value(value: &Value) -> Result<XmpTree> {
+        let schemas = match value.get("schemas") {
+            Some(Value::Array(schemas)) => schemas,
+            _ => return Err(Error::from(c::XmpError::BadValue)),
+        };
+        let mut tree = XmpTree::default();
+        for schema in schemas {
+            let object = schema
+                .as_object()
+                .ok_or_else(|| Error::from(c::XmpError::BadValue))?;
+            let mut schema = XmpSchema::new(
+                &json_string(object, "uri")?,
+                &json_string(object, "prefix")?,
+            );
+            schema.properties = json_nodes(object, "properties")?;
+            tree.schemas.push(schema);
+        }
+        Ok(tree)
+    }
+}
+
+impl Xmp {
+    /// Convert the packet to JSON. Requires the `json` feature.
+    ///
+    /// The format is lossless: the packet can be rebuilt with
+    /// `Xmp::from_json()`. The properties are listed by schema, and
+    /// `namespaces` has the prefixes of all the namespaces used.
+    ///
+    /// ```json
+    /// {
+    ///   "namespaces": {
+    ///     "dc": "http://purl.org/dc/elements/1.1/",
+    ///     "xml": "http://www.w3.org/XML/1998/namespace"
+    ///   },
+    ///   "schemas": [{
+    ///     "uri": "http://purl.org/dc/elements/1.1/",
+    ///     "prefix": "dc",
+    ///     "properties": [
+    ///       { "ns": "http://purl.org/dc/elements/1.1/", "name": "format", "value": "image/jpeg" },
+    ///       { "ns": "http://purl.org/dc/elements/1.1/", "name": "title", "array": "alt-text",
+    ///         "items": [{
+    ///           "value": "Hello",
+    ///           "qualifiers": [{ "ns": "http://www.w3.org/XML/1998/namespace", "name": "lang", "value": "x-default" }]
+    ///         }]
+    ///       }
+    ///     ]
+    ///   }]
+    /// }
+    /// ```
+    ///
+    /// Each node has:
+    /// - `ns` and `name`: the namespace uri and the name. Absent for
+    ///   the array items.
+    /// - `value`: the value of a simple node, and `uri` set to `true`
+    ///   if the value is a URI.
+    /// - `fields`: the fields of a struct.
+    /// - `array`: the form of an array, one of `bag`, `seq`, `alt` or
+    ///   `alt-text`, and `items`: the items.
+    /// - `qualifiers`: the qualifiers, if any.
+    pub fn to_json(&self) -> String {
+        format!("{:#}", self.to_tree().to_json_value())
+    }
+
+    /// Build a packet from the JSON of `Xmp::to_json()`. The namespaces
+    /// that aren't registered yet are registered, like in
+    /// `Xmp::from_tree()`. Requires the `json` feature.
+    ///
+    /// Return `BadParse` if the JSON is invalid and `BadValue` if it
+    /// doesn't follow the format.
+    pub fn from_json(json: &str) -> Result<Xmp> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|_| Error::from(c::XmpError::BadParse))?;
+        if let Some(namespaces) = value.get("namespaces").and_then(Value::as_object) {
+            for (prefix, uri) in namespaces {
+                if let Some(uri) = uri.as_str() {
+                    register_if_unknown(uri, prefix)?;
+                }
+            }
+        }
+        Xmp::from_tree(&XmpTree::from_json_value(&value)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::xmp::PropFlags;
+    use crate::xmptree::fixture::{alt_text, simple, structure, tree, NS};
+    use crate::xmptree::{XmpNode, XmpTree};
+
+    #[test]
+    fn test_json() {
+        let tree = tree(vec![
+            simple("test", "foo"),
+            alt_text("title", &[("x-default", "Hello")]),
+            structure(
+                "location",
+                vec![
+                    simple("city", "Montréal"),
+                    XmpNode::new(NS, "web", "http://example.com/", PropFlags::VALUE_IS_URI),
+                ],
+            ),
+        ]);
+
+        let json = tree.to_json_value();
+        assert_eq!(
+            json["schemas"][0]["properties"][0],
+            serde_json::json!({"ns": NS, "name": "test", "value": "foo"})
+        );
+        assert_eq!(json["schemas"][0]["properties"][1]["array"], "alt-text");
+        assert_eq!(json["namespaces"]["rust"], NS);
+        assert_eq!(XmpTree::from_json_value(&json), Ok(tree));
+
+        assert!(XmpTree::from_json_value(&serde_json::json!({"schemas": 1})).is_err());
+        assert!(XmpTree::from_json_value(&serde_json::json!({
+            "schemas": [{"uri": NS, "properties": [{"array": "list"}]}]
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmptree::{XmpNode, XmpNodeKind, XmpTree};
+use crate::Result;
+use crate::NS_XML_URI;
+
+/// What to do with a property that exists in both packets.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum MergeConflict {
+    /// Keep the existing value.
+    #[default]
+    KeepExisting,
+    /// Overwrite the existing value with the other one.
+    Overwrite,
+}
+
+/// How to merge packets.
+///
+/// Properties that only exist in the other packet are always added,
+/// and struct fields and qualifiers are merged one by one. The conflict
+/// rule decides what happen to a value that exists in both, the array
+/// options how the arrays that exist in both are combined.
+///
+/// ```
+/// use exempi2::{MergeConflict, MergePolicy};
+///
+/// let policy = MergePolicy::new(MergeConflict::Overwrite)
+///     .union_bags(true)
+///     .merge_alt_text(true);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MergePolicy {
+    conflict: MergeConflict,
+    union_bags: bool,
+    merge_alt_text: bool,
+    replace_arrays: bool,
+}
+
+impl MergePolicy {
+    /// New policy with the conflict rule, and the arrays following it.
+    pub fn new(conflict: MergeConflict) -> MergePolicy {
+        MergePolicy {
+            conflict,
+            ..MergePolicy::default()
+        }
+    }
+
+    /// Bag arrays get the union of the items.
+    pub fn union_bags(mut self, union_bags: bool) -> MergePolicy {
+        self.union_bags = union_bags;
+        self
+    }
+
+    /// Alt-text arrays are merged per language. Existing languages
+    /// are overwritten only with `MergeConflict::Overwrite`.
+    pub fn merge_alt_text(mut self, merge_alt_text: bool) -> MergePolicy {
+        self.merge_alt_text = merge_alt_text;
+        self
+    }
+
+    /// The other arrays replace the existing ones, whatever the
+    /// conflict rule. `union_bags()` and `merge_alt_text()` apply first.
+    pub fn replace_arrays(mut self, replace_arrays: bool) -> MergePolicy {
+        self.replace_arrays = replace_arrays;
+        self
+    }
+}
+
+fn lang(item: &XmpNode) -> Option<&str> {
+    item.qualifier(NS_XML_URI, "lang")
+        .map(|lang| lang.value.as_str())
+}
+
+/// Merge `src` into `dest`. A node that is replaced takes the
+/// qualifiers of `src`, otherwise the qualifiers are merged.
+fn merge_node(dest: &mut XmpNode, src: &XmpNode, policy: MergePolicy) {
+    let overwrite = policy.conflict == MergeConflict::Overwrite;
+    match (&mut dest.kind, &src.kind) {
+        (XmpNodeKind::Struct(dest_fields), XmpNodeKind::Struct(src_fields)) => {
+            merge_named(dest_fields, src_fields, policy)
+        }
+        (
+            XmpNodeKind::Array(ArrayForm::Bag, dest_items),
+            XmpNodeKind::Array(ArrayForm::Bag, src_items),
+        ) if policy.union_bags => {
+            for item in src_items {
+                if !dest_items.contains(item) {
+                    dest_items.push(item.clone());
+                }
+            }
+        }
+        (
+            XmpNodeKind::Array(ArrayForm::AltText, dest_items),
+            XmpNodeKind::Array(ArrayForm::AltText, src_items),
+        ) if policy.merge_alt_text => {
+            for item in src_items {
+                match dest_items.iter_mut().find(|i| lang(i) == lang(item)) {
+                    Some(dest_item) if overwrite => *dest_item = item.clone(),
+                    Some(_) => {}
+                    // x-default is always the first item.
+                    None if lang(item) == Some("x-default") => dest_items.insert(0, item.clone()),
+                    None => dest_items.push(item.clone()),
+                }
+            }
+        }
+        (XmpNodeKind::Array(..), _) | (_, XmpNodeKind::Array(..)) if policy.replace_arrays => {
+            *dest = src.clone();
+            return;
+        }
+        _ if overwrite => {
+            *dest = src.clone();
+            return;
+        }
+        _ => {}
+    }
+    merge_named(&mut dest.qualifiers, &src.qualifiers, policy);
+    if !dest.qualifiers.is_empty() {
+        dest.flags |= PropFlags::HAS_QUALIFIERS;
+    }
+}
+
+/// Merge the named nodes, properties or fields.
+fn merge_named(dest: &mut Vec<XmpNode>, src: &[XmpNode], policy: MergePolicy) {
+    for node in src {
+        match dest
+            .iter_mut()
+            .find(|n| n.ns == node.ns && n.name == node.name)
+        {
+            Some(dest_node) => merge_node(dest_node, node, policy),
+            None => dest.push(node.clone()),
+        }
+    }
+}
+
+impl XmpTree {
+    /// Merge `other` into `self`, following the policy.
+    pub fn merge(&mut self, other: &XmpTree, policy: MergePolicy) {
+        for schema in &other.schemas {
+            match self.schemas.iter_mut().find(|s| s.uri == schema.uri) {
+                Some(dest) => merge_named(&mut dest.properties, &schema.properties, policy),
+                None => self.schemas.push(schema.clone()),
+            }
+        }
+    }
+}
+
+impl Xmp {
+    /// Merge the properties of `other`, in all the namespaces, into
+    /// this packet, following the policy. Like the XMP Toolkit
+    /// `AppendProperties()`, but the policy is more detailed.
+    ///
+    /// The change is atomic: on error the packet is left untouched.
+    ///
+    /// ```no_run
+    /// use exempi2::{MergeConflict, MergePolicy, Xmp};
+    ///
+    /// let mut camera = Xmp::new();
+    /// let template = Xmp::new();
+    /// let policy = MergePolicy::new(MergeConflict::KeepExisting)
+    ///     .union_bags(true)
+    ///     .merge_alt_text(true);
+    /// camera.merge(&template, policy).unwrap();
+    /// ```
+    pub fn merge(&mut self, other: &Xmp, policy: MergePolicy) -> Result<()> {
+        let original = self.to_tree();
+        let mut tree = original.clone();
+        tree.merge(&other.to_tree(), policy);
+
+        let mut xmp = self.clone();
+        for schema in &tree.schemas {
+            // Only write the properties that changed.
+            for property in &schema.properties {
+                let existing = original
+                    .schema(&schema.uri)
+                    .and_then(|s| s.property(&property.name));
+                if existing != Some(property) {
+                    xmp.set_node(&schema.uri, &property.name, property)?;
+                }
+            }
+        }
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MergeConflict, MergePolicy};
+    use crate::xmp::ArrayForm;
+    use crate::xmptree::fixture::{array, qualified, qualifier, simple, tree, NS};
+    use crate::xmptree::XmpNode;
+
+    fn bag(items: &[&str]) -> XmpNode {
+        array("bag", ArrayForm::Bag, items)
+    }
+
+    #[test]
+    fn test_merge() {
+        let dest = tree(vec![simple("test", "foo"), bag(&["a", "b"])]);
+        let src = tree(vec![
+            simple("test", "bar"),
+            simple("new", "baz"),
+            bag(&["b", "c"]),
+        ]);
+
+        let mut merged = dest.clone();
+        merged.merge(&src, MergePolicy::default());
+        let schema = &merged.schemas[0];
+        assert_eq!(schema.property("test").unwrap().value, "foo");
+        assert_eq!(schema.property("new").unwrap().value, "baz");
+        assert_eq!(schema.property("bag"), Some(&bag(&["a", "b"])));
+
+        let mut merged = dest.clone();
+        merged.merge(&src, MergePolicy::default().union_bags(true));
+        let schema = &merged.schemas[0];
+        assert_eq!(schema.property("test").unwrap().value, "foo");
+        assert_eq!(schema.property("bag"), Some(&bag(&["a", "b", "c"])));
+
+        let mut merged = dest.clone();
+        merged.merge(&src, MergePolicy::default().replace_arrays(true));
+        let schema = &merged.schemas[0];
+        assert_eq!(schema.property("test").unwrap().value, "foo");
+        assert_eq!(schema.property("bag"), Some(&bag(&["b", "c"])));
+
+        let mut merged = dest;
+        merged.merge(&src, MergePolicy::new(MergeConflict::Overwrite));
+        let schema = &merged.schemas[0];
+        assert_eq!(schema.property("test").unwrap().value, "bar");
+        assert_eq!(schema.property("bag"), Some(&bag(&["b", "c"])));
+    }
+
+    #[test]
+    fn test_merge_qualifiers() {
+        let dest = tree(vec![qualified(
+            simple("test", "foo"),
+            vec![qualifier("q1", "a")],
+        )]);
+        let src = tree(vec![qualified(
+            simple("test", "bar"),
+            vec![qualifier("q1", "b"), qualifier("q2", "c")],
+        )]);
+
+        // The existing value is kept, the qualifiers are merged.
+        let mut merged = dest.clone();
+        merged.merge(&src, MergePolicy::default());
+        let test = merged.schemas[0].property("test").unwrap();
+        assert_eq!(test.value, "foo");
+        assert_eq!(test.qualifier(NS, "q1").unwrap().value, "a");
+        assert_eq!(test.qualifier(NS, "q2").unwrap().value, "c");
+
+        // The replaced value takes the qualifiers.
+        let mut merged = dest;
+        merged.merge(&src, MergePolicy::new(MergeConflict::Overwrite));
+        assert_eq!(merged, src);
+    }
+}
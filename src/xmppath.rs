@@ -0,0 +1,241 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use crate::error::Error;
+use crate::xmpstring::XmpString;
+use crate::Result;
+
+/// A step in a path, after the property name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Step {
+    /// Struct field: namespace uri and name.
+    Field(String, String),
+    /// Array item, 1-based.
+    Index(i32),
+    /// Last item of an array.
+    Last,
+    /// Qualifier: namespace uri and name.
+    Qualifier(String, String),
+    /// Alt-text item selected by its xml:lang qualifier.
+    Lang(String),
+}
+
+/// Path to a property inside an XMP packet.
+///
+/// This compose the path expressions understood by the XMP Toolkit
+/// without having to write them by hand. Namespaces of the fields and
+/// qualifiers are given as uri and are resolved to their registered
+/// prefix when the path is used.
+///
+/// ```no_run
+/// use exempi2::{Xmp, XmpPath};
+///
+/// let xmp = Xmp::new();
+///
+/// // exif:Flash/exif:Fired
+/// let fired = XmpPath::new("Flash").field("http://ns.adobe.com/exif/1.0/", "Fired");
+/// let value = xmp.get_property("http://ns.adobe.com/exif/1.0/", &fired);
+///
+/// // dc:creator[3]
+/// let creator = XmpPath::new("creator").index(3);
+/// // dc:title[?xml:lang="fr"]
+/// let title = XmpPath::new("title").lang("fr");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct XmpPath {
+    name: String,
+    steps: Vec<Step>,
+}
+
+impl XmpPath {
+    /// New path for the property `name`. The name can be
+    /// prefixed or not. If not, the prefix of the schema is implied.
+    pub fn new(name: &str) -> XmpPath {
+        XmpPath {
+            name: name.to_owned(),
+            steps: vec![],
+        }
+    }
+
+    /// Select the field `name` from the namespace `ns` of a struct.
+    pub fn field(mut self, ns: &str, name: &str) -> XmpPath {
+        self.steps.push(Step::Field(ns.to_owned(), name.to_owned()));
+        self
+    }
+
+    /// Select the array item at `index`. Arrays are indexed from 1.
+    pub fn index(mut self, index: i32) -> XmpPath {
+        self.steps.push(Step::Index(index));
+        self
+    }
+
+    /// Select the last item of an array.
+    pub fn last(mut self) -> XmpPath {
+        self.steps.push(Step::Last);
+        self
+    }
+
+    /// Select the qualifier `name` from the namespace `ns`.
+    pub fn qualifier(mut self, ns: &str, name: &str) -> XmpPath {
+        self.steps
+            .push(Step::Qualifier(ns.to_owned(), name.to_owned()));
+        self
+    }
+
+    /// Select the item of an alt-text array for the language `lang`.
+    pub fn lang(mut self, lang: &str) -> XmpPath {
+        self.steps.push(Step::Lang(lang.to_owned()));
+        self
+    }
+
+    /// Compose the path expression.
+    /// Will return `BadSchema` if a namespace isn't registered,
+    /// `BadIndex` if an array index is out of range and `BadXPath` if
+    /// a language contains a `"` or a `]`.
+    pub fn compose(&self) -> Result<String> {
+        let mut path = self.name.clone();
+        for step in &self.steps {
+            match step {
+                Step::Field(ns, name) => {
+                    path.push('/');
+                    path.push_str(&qualified_name(ns, name)?);
+                }
+                Step::Index(index) => {
+                    if *index < 1 {
+                        return Err(Error::from(c::XmpError::BadIndex));
+                    }
+                    path.push_str(&format!("[{}]", index));
+                }
+                Step::Last => path.push_str("[last()]"),
+                Step::Qualifier(ns, name) => {
+                    path.push_str("/?");
+                    path.push_str(&qualified_name(ns, name)?);
+                }
+                Step::Lang(lang) => {
+                    // They would end the selector.
+                    if lang.contains(['"', ']']) {
+                        return Err(Error::from(c::XmpError::BadXPath));
+                    }
+                    path.push_str(&format!("[?xml:lang=\"{}\"]", lang));
+                }
+            }
+        }
+        Ok(path)
+    }
+}
+
+/// Return `prefix:name` for the name in the namespace `ns`.
+fn qualified_name(ns: &str, name: &str) -> Result<String> {
+    let prefix = crate::namespace_prefix(ns).map_err(|_| Error::from(c::XmpError::BadSchema))?;
+    // The toolkit return the prefix with the colon.
+    let prefix = String::from(&prefix);
+    let prefix = prefix.trim_end_matches(':');
+    if prefix.is_empty() {
+        return Err(Error::from(c::XmpError::BadSchema));
+    }
+    Ok(format!("{}:{}", prefix, name))
+}
+
+/// Trait for the types that can name a property.
+/// Strings are used as is, `XmpPath` is composed.
+pub trait ToXmpPath {
+    /// Return the path expression.
+    fn to_xmp_path(&self) -> Result<String>;
+}
+
+impl ToXmpPath for XmpPath {
+    fn to_xmp_path(&self) -> Result<String> {
+        self.compose()
+    }
+}
+
+impl ToXmpPath for str {
+    fn to_xmp_path(&self) -> Result<String> {
+        Ok(self.to_owned())
+    }
+}
+
+impl ToXmpPath for String {
+    fn to_xmp_path(&self) -> Result<String> {
+        Ok(self.clone())
+    }
+}
+
+impl ToXmpPath for XmpString {
+    fn to_xmp_path(&self) -> Result<String> {
+        self.to_str()
+            .map(|s| s.to_owned())
+            .map_err(|_| Error::from(c::XmpError::BadXPath))
+    }
+}
+
+impl ToXmpPath for [u8] {
+    fn to_xmp_path(&self) -> Result<String> {
+        std::str::from_utf8(self)
+            .map(|s| s.to_owned())
+            .map_err(|_| Error::from(c::XmpError::BadXPath))
+    }
+}
+
+impl<const N: usize> ToXmpPath for [u8; N] {
+    fn to_xmp_path(&self) -> Result<String> {
+        self[..].to_xmp_path()
+    }
+}
+
+impl ToXmpPath for Vec<u8> {
+    fn to_xmp_path(&self) -> Result<String> {
+        self[..].to_xmp_path()
+    }
+}
+
+impl<T: ToXmpPath + ?Sized> ToXmpPath for &T {
+    fn to_xmp_path(&self) -> Result<String> {
+        (**self).to_xmp_path()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ToXmpPath, XmpPath};
+    use crate::error::Error;
+
+    #[test]
+    fn test_xmppath() {
+        assert_eq!(
+            XmpPath::new("dc:creator").compose(),
+            Ok("dc:creator".to_owned())
+        );
+        assert_eq!(
+            XmpPath::new("creator").index(3).compose(),
+            Ok("creator[3]".to_owned())
+        );
+        assert_eq!(
+            XmpPath::new("creator").last().compose(),
+            Ok("creator[last()]".to_owned())
+        );
+        assert_eq!(
+            XmpPath::new("title").lang("fr").compose(),
+            Ok("title[?xml:lang=\"fr\"]".to_owned())
+        );
+        assert_eq!(
+            XmpPath::new("title").lang("fr\"]/x").compose(),
+            Err(Error::from(c::XmpError::BadXPath))
+        );
+        assert_eq!(
+            XmpPath::new("title").lang("fr]").compose(),
+            Err(Error::from(c::XmpError::BadXPath))
+        );
+        assert_eq!(
+            XmpPath::new("creator").index(0).compose(),
+            Err(Error::from(c::XmpError::BadIndex))
+        );
+
+        assert_eq!("dc:title".to_xmp_path(), Ok("dc:title".to_owned()));
+        assert_eq!([].to_xmp_path(), Ok("".to_owned()));
+    }
+}
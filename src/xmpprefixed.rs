@@ -0,0 +1,237 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use crate::error::Error;
+use crate::xmp::{PropFlags, Xmp};
+use crate::xmpiterator::{IterFlags, XmpIterator};
+use crate::xmpstring::XmpString;
+use crate::xmpvalue::{FromXmpValue, Property, ToXmpValue};
+use crate::Result;
+
+/// Return the namespace uri registered for `prefix`, or `BadSchema`.
+fn namespace_for(prefix: &str) -> Result<String> {
+    if prefix.is_empty() {
+        return Err(Error::from(c::XmpError::BadXPath));
+    }
+    crate::prefix_namespace(prefix)
+        .map(|uri| String::from(&uri))
+        .ok()
+        .filter(|uri| !uri.is_empty())
+        .ok_or_else(|| Error::from(c::XmpError::BadSchema))
+}
+
+/// Resolve the prefixed path, like `exif:Flash/exif:Fired`, to the
+/// namespace uri of the property. All the prefixes of the path are
+/// checked, including the ones in the selectors, like
+/// `[?xml:lang="fr"]`. Return `BadXPath` if a name isn't prefixed or the
+/// path is malformed, and `BadSchema` if a prefix isn't registered.
+fn resolve_path(path: &str) -> Result<String> {
+    resolve_path_with(path, namespace_for)
+}
+
+/// Resolve the prefixed path with `namespace_for`. See `resolve_path()`.
+fn resolve_path_with<F>(path: &str, namespace_for: F) -> Result<String>
+where
+    F: Fn(&str) -> Result<String>,
+{
+    let bad_path = || Error::from(c::XmpError::BadXPath);
+    // Check the prefixed name, or qualifier name, and return its
+    // namespace uri.
+    let check_name = |name: &str| {
+        let name = name.strip_prefix('?').unwrap_or(name);
+        match name.split_once(':') {
+            Some((prefix, local)) if !local.is_empty() && !local.contains(':') => {
+                namespace_for(prefix)
+            }
+            _ => Err(bad_path()),
+        }
+    };
+
+    let mut schema = None;
+    let mut rest = path;
+    loop {
+        let end = rest.find(['/', '[']).unwrap_or(rest.len());
+        let uri = check_name(&rest[..end])?;
+        schema.get_or_insert(uri);
+        rest = &rest[end..];
+        // The array selectors, like [2], [last()] or [rust:field="v"].
+        while let Some(selector) = rest.strip_prefix('[') {
+            let end = selector_end(selector).ok_or_else(bad_path)?;
+            let selector = &selector[..end];
+            if !(selector == "last()" || is_index(selector)) {
+                let (name, value) = selector.split_once('=').ok_or_else(bad_path)?;
+                if !is_quoted(value) {
+                    return Err(bad_path());
+                }
+                check_name(name)?;
+            }
+            rest = &rest[end + 2..];
+        }
+        match rest.strip_prefix('/') {
+            Some(next) => rest = next,
+            None if rest.is_empty() => break,
+            None => return Err(bad_path()),
+        }
+    }
+    schema.ok_or_else(bad_path)
+}
+
+/// Return the offset of the `]` closing the selector, skipping the
+/// quoted values.
+fn selector_end(selector: &str) -> Option<usize> {
+    let mut quote = None;
+    for (offset, ch) in selector.char_indices() {
+        match (quote, ch) {
+            (None, '"') | (None, '\'') => quote = Some(ch),
+            (Some(q), _) if q == ch => quote = None,
+            (None, ']') => return Some(offset),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether `selector` is an array index.
+fn is_index(selector: &str) -> bool {
+    !selector.is_empty() && selector.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Whether `value` is quoted.
+fn is_quoted(value: &str) -> bool {
+    value.len() >= 2
+        && (value.starts_with('"') && value.ends_with('"')
+            || value.starts_with('\'') && value.ends_with('\''))
+}
+
+impl Xmp {
+    /// Get the property at the prefixed path, like `dc:format` or
+    /// `exif:Flash/exif:Fired`. See `Xmp::get()`.
+    ///
+    /// The prefixes are resolved with `prefix_namespace()`: `BadSchema`
+    /// is returned if one isn't registered.
+    ///
+    /// ```no_run
+    /// use exempi2::Xmp;
+    ///
+    /// let xmp = Xmp::new();
+    /// let fired = xmp.get_prefixed::<bool>("exif:Flash/exif:Fired");
+    /// ```
+    pub fn get_prefixed<T: FromXmpValue>(&self, path: &str) -> Result<Option<T>> {
+        self.get(&resolve_path(path)?, path)
+    }
+
+    /// Set the property at the prefixed path from `value`. See
+    /// `Xmp::set()` and `Xmp::get_prefixed()`.
+    pub fn set_prefixed<T: ToXmpValue + ?Sized>(&mut self, path: &str, value: &T) -> Result<()> {
+        self.set(&resolve_path(path)?, path, value)
+    }
+
+    /// Get the property at the prefixed path as a XmpString. See
+    /// `Xmp::get_prefixed()`.
+    pub fn get_property_prefixed(&self, path: &str) -> Result<Option<Property<XmpString>>> {
+        self.get_property(&resolve_path(path)?, path)
+    }
+
+    /// Set the property at the prefixed path. See
+    /// `Xmp::get_prefixed()`.
+    pub fn set_property_prefixed(
+        &mut self,
+        path: &str,
+        value: &str,
+        optionbits: PropFlags,
+    ) -> Result<()> {
+        self.set_property(&resolve_path(path)?, path, value, optionbits)
+    }
+
+    /// Delete the property at the prefixed path. See
+    /// `Xmp::get_prefixed()`.
+    pub fn delete_property_prefixed(&mut self, path: &str) -> Result<()> {
+        self.delete_property(&resolve_path(path)?, path)
+    }
+
+    /// Whether the packet has the property at the prefixed path. Unlike
+    /// `Xmp::has_property()`, an unregistered prefix is an error, and
+    /// not a missing property.
+    pub fn has_property_prefixed(&self, path: &str) -> Result<bool> {
+        Ok(self.has_property(&resolve_path(path)?, path))
+    }
+}
+
+impl XmpIterator {
+    /// New iterator on the property at the prefixed path, like
+    /// `Xmp::get_prefixed()`. A prefix alone, like `dc:`, iterates on
+    /// the whole schema.
+    ///
+    /// ```no_run
+    /// use exempi2::{IterFlags, Xmp, XmpIterator};
+    ///
+    /// let xmp = Xmp::new();
+    /// let iter = XmpIterator::new_prefixed(&xmp, "dc:", IterFlags::PROPERTIES).unwrap();
+    /// ```
+    pub fn new_prefixed(xmp: &Xmp, path: &str, propsbits: IterFlags) -> Result<XmpIterator> {
+        match path.strip_suffix(':') {
+            Some(prefix) if !prefix.contains(['/', ':', '[']) => Ok(XmpIterator::with_path(
+                xmp,
+                namespace_for(prefix)?,
+                "",
+                propsbits,
+            )),
+            _ => Ok(XmpIterator::with_path(
+                xmp,
+                resolve_path(path)?,
+                path,
+                propsbits,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::resolve_path_with;
+    use crate::error::Error;
+
+    const NS: &str = "http://rust.figuiere.net/ns/rust/";
+
+    fn resolve(path: &str) -> crate::Result<String> {
+        resolve_path_with(path, |prefix| match prefix {
+            "rust" => Ok(NS.to_owned()),
+            "xml" => Ok(crate::NS_XML_URI.to_owned()),
+            _ => Err(Error::from(c::XmpError::BadSchema)),
+        })
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let bad_path = Err(Error::from(c::XmpError::BadXPath));
+        let bad_schema = Err(Error::from(c::XmpError::BadSchema));
+
+        assert_eq!(resolve("rust:test"), Ok(NS.to_owned()));
+        assert_eq!(resolve("rust:bag[2]/rust:field"), Ok(NS.to_owned()));
+        assert_eq!(resolve("rust:bag[last()]/?xml:lang"), Ok(NS.to_owned()));
+        assert_eq!(resolve("rust:title[?xml:lang=\"fr\"]"), Ok(NS.to_owned()));
+        // A '/' or ']' inside a quoted value doesn't end the step.
+        assert_eq!(
+            resolve("rust:bag[rust:field=\"a/b]c\"]/rust:zip"),
+            Ok(NS.to_owned())
+        );
+        assert_eq!(resolve("rust:bag[rust:field='it''s']"), Ok(NS.to_owned()));
+
+        // The prefixes in the selectors are checked.
+        assert_eq!(resolve("rust:bag[nosuchprefix:field=\"v\"]"), bad_schema);
+        assert_eq!(resolve("rust:bag[?nosuchprefix:q=\"v\"]"), bad_schema);
+        assert_eq!(resolve("rust:bag[field=\"v\"]"), bad_path);
+
+        assert_eq!(resolve("rust:bag[rust:field=v]"), bad_path);
+        assert_eq!(resolve("rust:bag[rust:field=\"v]"), bad_path);
+        assert_eq!(resolve("rust:bag[]"), bad_path);
+        assert_eq!(resolve("rust:bag[1]x"), bad_path);
+        assert_eq!(resolve("rust:test/"), bad_path);
+        assert_eq!(resolve("test"), bad_path);
+        assert_eq!(resolve(""), bad_path);
+    }
+}
@@ -0,0 +1,503 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmptree::{XmpNode, XmpNodeKind, XmpTree};
+use crate::{NS_RDF_URI, NS_XML_URI};
+
+/// The name of the RDF container for the array form.
+fn container_name(form: ArrayForm) -> &'static str {
+    match form {
+        ArrayForm::Bag => "Bag",
+        ArrayForm::Seq => "Seq",
+        ArrayForm::Alt | ArrayForm::AltText => "Alt",
+    }
+}
+
+/// The language of a node and its other qualifiers. RDF has
+/// language-tagged literals for xml:lang.
+fn split_qualifiers(node: &XmpNode) -> (Option<&str>, Vec<&XmpNode>) {
+    let mut lang = None;
+    let mut others = vec![];
+    for qualifier in &node.qualifiers {
+        if node.kind == XmpNodeKind::Simple
+            && qualifier.ns == NS_XML_URI
+            && qualifier.name == "lang"
+        {
+            lang = Some(qualifier.value.as_str());
+        } else {
+            others.push(qualifier);
+        }
+    }
+    (lang, others)
+}
+
+/// Resolve the namespace prefixes, and record the ones used.
+struct Prefixes<'a> {
+    tree: &'a XmpTree,
+    /// The prefixes used, with their namespace.
+    used: BTreeMap<String, String>,
+}
+
+impl<'a> Prefixes<'a> {
+    fn new(tree: &'a XmpTree) -> Prefixes<'a> {
+        Prefixes {
+            tree,
+            used: BTreeMap::new(),
+        }
+    }
+
+    /// Return `prefix:name` if the prefix of `ns` is known.
+    /// The prefixes of the schemas in the tree are used first.
+    fn compact(&mut self, ns: &str, name: &str) -> Option<String> {
+        let prefix = self.tree.prefix_for(ns)?;
+        let qname = format!("{}:{}", prefix, name);
+        self.used.insert(prefix, ns.to_owned());
+        Some(qname)
+    }
+}
+
+/// A term of a triple.
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    /// The IRI made of a namespace and a name.
+    Name(String, String),
+    /// An IRI.
+    Iri(String),
+    /// A blank node. 0 is the subject when it has no IRI.
+    Blank(usize),
+    /// A literal, with an optional language.
+    Literal(String, Option<String>),
+}
+
+impl Term {
+    fn rdf(name: &str) -> Term {
+        Term::Name(NS_RDF_URI.to_owned(), name.to_owned())
+    }
+}
+
+/// Escape a string for a N-Triples or Turtle literal.
+fn escape_literal(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape an IRI, the characters that aren't allowed are written as
+/// `\uXXXX`.
+fn escape_iri(iri: &str) -> String {
+    let mut escaped = String::with_capacity(iri.len());
+    for c in iri.chars() {
+        if c <= ' ' || "<>\"{}|^`\\".contains(c) {
+            let _ = write!(escaped, "\\u{:04X}", c as u32);
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Whether the name can be the local part of a Turtle prefixed name.
+fn is_turtle_local_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.ends_with('.')
+        && !name.starts_with(['.', '-'])
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// The triples of a tree.
+struct Graph {
+    triples: Vec<(Term, Term, Term)>,
+    blanks: usize,
+}
+
+impl Graph {
+    fn new(tree: &XmpTree, subject: &str) -> Graph {
+        let subject = if subject.is_empty() {
+            Term::Blank(0)
+        } else {
+            Term::Iri(subject.to_owned())
+        };
+        let mut graph = Graph {
+            triples: vec![],
+            blanks: 0,
+        };
+        for schema in &tree.schemas {
+            for property in &schema.properties {
+                graph.add(subject.clone(), property);
+            }
+        }
+        // Group the triples by subject, in the order they were created.
+        graph.triples.sort_by_key(|(subject, _, _)| match subject {
+            Term::Blank(id) => *id,
+            _ => 0,
+        });
+        graph
+    }
+
+    fn blank(&mut self) -> Term {
+        self.blanks += 1;
+        Term::Blank(self.blanks)
+    }
+
+    /// Add the triple for the named node.
+    fn add(&mut self, subject: Term, node: &XmpNode) {
+        let predicate = Term::Name(node.ns.clone(), node.name.clone());
+        let object = self.object(node);
+        self.triples.push((subject, predicate, object));
+    }
+
+    /// Return the object for the node, adding the triples of its
+    /// children. Structs and arrays are blank nodes, and so are the
+    /// nodes with qualifiers, with the value as `rdf:value`.
+    fn object(&mut self, node: &XmpNode) -> Term {
+        let (lang, qualifiers) = split_qualifiers(node);
+        let qualified = if qualifiers.is_empty() {
+            None
+        } else {
+            Some(self.blank())
+        };
+        let value = match node.kind {
+            XmpNodeKind::Simple if node.flags.contains(PropFlags::VALUE_IS_URI) => {
+                Term::Iri(node.value.clone())
+            }
+            XmpNodeKind::Simple => Term::Literal(node.value.clone(), lang.map(str::to_owned)),
+            XmpNodeKind::Struct(ref fields) => {
+                let blank = self.blank();
+                for field in fields {
+                    self.add(blank.clone(), field);
+                }
+                blank
+            }
+            XmpNodeKind::Array(form, ref items) => {
+                let blank = self.blank();
+                self.triples.push((
+                    blank.clone(),
+                    Term::rdf("type"),
+                    Term::rdf(container_name(form)),
+                ));
+                for (index, item) in items.iter().enumerate() {
+                    let object = self.object(item);
+                    self.triples.push((
+                        blank.clone(),
+                        Term::rdf(&format!("_{}", index + 1)),
+                        object,
+                    ));
+                }
+                blank
+            }
+        };
+        match qualified {
+            Some(blank) => {
+                self.triples
+                    .push((blank.clone(), Term::rdf("value"), value));
+                for qualifier in qualifiers {
+                    self.add(blank.clone(), qualifier);
+                }
+                blank
+            }
+            None => value,
+        }
+    }
+
+    /// Write a term. With `prefixes`, the names are written as
+    /// prefixed names when possible, for Turtle.
+    fn write_term(out: &mut String, term: &Term, prefixes: Option<&mut Prefixes>) {
+        let _ = match term {
+            Term::Name(ns, name) => {
+                let qname = prefixes
+                    .filter(|_| is_turtle_local_name(name))
+                    .and_then(|prefixes| prefixes.compact(ns, name));
+                match qname {
+                    Some(qname) => write!(out, "{}", qname),
+                    None => write!(out, "<{}{}>", escape_iri(ns), escape_iri(name)),
+                }
+            }
+            Term::Iri(iri) => write!(out, "<{}>", escape_iri(iri)),
+            Term::Blank(id) => write!(out, "_:b{}", id),
+            Term::Literal(value, Some(lang)) => {
+                write!(out, "\"{}\"@{}", escape_literal(value), lang)
+            }
+            Term::Literal(value, None) => write!(out, "\"{}\"", escape_literal(value)),
+        };
+    }
+
+    fn to_ntriples(&self) -> String {
+        let mut out = String::new();
+        for (subject, predicate, object) in &self.triples {
+            Graph::write_term(&mut out, subject, None);
+            out.push(' ');
+            Graph::write_term(&mut out, predicate, None);
+            out.push(' ');
+            Graph::write_term(&mut out, object, None);
+            out.push_str(" .\n");
+        }
+        out
+    }
+
+    fn to_turtle(&self, tree: &XmpTree) -> String {
+        let mut prefixes = Prefixes::new(tree);
+        let mut body = String::new();
+        let mut last_subject = None;
+        for (subject, predicate, object) in &self.triples {
+            if last_subject == Some(subject) {
+                body.push_str(" ;\n    ");
+            } else {
+                if last_subject.is_some() {
+                    body.push_str(" .\n\n");
+                }
+                Graph::write_term(&mut body, subject, None);
+                body.push(' ');
+                last_subject = Some(subject);
+            }
+            Graph::write_term(&mut body, predicate, Some(&mut prefixes));
+            body.push(' ');
+            Graph::write_term(&mut body, object, Some(&mut prefixes));
+        }
+        if last_subject.is_some() {
+            body.push_str(" .\n");
+        }
+
+        let mut out = String::new();
+        for (prefix, ns) in &prefixes.used {
+            let _ = writeln!(out, "@prefix {}: <{}> .", prefix, escape_iri(ns));
+        }
+        if !prefixes.used.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&body);
+        out
+    }
+}
+
+#[cfg(feature = "json")]
+mod json_ld {
+    use serde_json::{Map, Value};
+
+    use super::{container_name, split_qualifiers, Prefixes};
+    use crate::xmp::PropFlags;
+    use crate::xmptree::{XmpNode, XmpNodeKind, XmpTree};
+    use crate::NS_RDF_URI;
+
+    /// The JSON-LD key for the name: a compact IRI, or the full IRI.
+    fn key(prefixes: &mut Prefixes, ns: &str, name: &str) -> String {
+        prefixes
+            .compact(ns, name)
+            .unwrap_or_else(|| format!("{}{}", ns, name))
+    }
+
+    fn add(object: &mut Map<String, Value>, prefixes: &mut Prefixes, node: &XmpNode) {
+        let key = key(prefixes, &node.ns, &node.name);
+        let value = to_value(prefixes, node);
+        object.insert(key, value);
+    }
+
+    fn to_value(prefixes: &mut Prefixes, node: &XmpNode) -> Value {
+        let (lang, qualifiers) = split_qualifiers(node);
+        let value = match node.kind {
+            XmpNodeKind::Simple if node.flags.contains(PropFlags::VALUE_IS_URI) => {
+                let mut object = Map::new();
+                object.insert("@id".into(), node.value.clone().into());
+                Value::Object(object)
+            }
+            XmpNodeKind::Simple => match lang {
+                Some(lang) => {
+                    let mut object = Map::new();
+                    object.insert("@value".into(), node.value.clone().into());
+                    object.insert("@language".into(), lang.into());
+                    Value::Object(object)
+                }
+                None => node.value.clone().into(),
+            },
+            XmpNodeKind::Struct(ref fields) => {
+                let mut object = Map::new();
+                for field in fields {
+                    add(&mut object, prefixes, field);
+                }
+                Value::Object(object)
+            }
+            XmpNodeKind::Array(form, ref items) => {
+                let mut object = Map::new();
+                object.insert(
+                    "@type".into(),
+                    key(prefixes, NS_RDF_URI, container_name(form)).into(),
+                );
+                for (index, item) in items.iter().enumerate() {
+                    let key = key(prefixes, NS_RDF_URI, &format!("_{}", index + 1));
+                    let value = to_value(prefixes, item);
+                    object.insert(key, value);
+                }
+                Value::Object(object)
+            }
+        };
+        if qualifiers.is_empty() {
+            return value;
+        }
+        let mut object = Map::new();
+        object.insert(key(prefixes, NS_RDF_URI, "value"), value);
+        for qualifier in qualifiers {
+            add(&mut object, prefixes, qualifier);
+        }
+        Value::Object(object)
+    }
+
+    pub(super) fn to_json_ld(tree: &XmpTree, subject: &str) -> Value {
+        let mut prefixes = Prefixes::new(tree);
+        let mut object = Map::new();
+        if !subject.is_empty() {
+            object.insert("@id".into(), subject.into());
+        }
+        for schema in &tree.schemas {
+            for property in &schema.properties {
+                add(&mut object, &mut prefixes, property);
+            }
+        }
+
+        let mut context = Map::new();
+        for (prefix, ns) in prefixes.used {
+            context.insert(prefix, ns.into());
+        }
+        let mut document = Map::new();
+        document.insert("@context".into(), Value::Object(context));
+        document.extend(object);
+        Value::Object(document)
+    }
+}
+
+impl XmpTree {
+    /// Export the RDF graph as N-Triples. See `Xmp::to_ntriples()`.
+    pub fn to_ntriples(&self, subject: &str) -> String {
+        Graph::new(self, subject).to_ntriples()
+    }
+
+    /// Export the RDF graph as Turtle. See `Xmp::to_turtle()`.
+    pub fn to_turtle(&self, subject: &str) -> String {
+        Graph::new(self, subject).to_turtle(self)
+    }
+
+    /// Export the RDF graph as JSON-LD. See `Xmp::to_json_ld()`.
+    #[cfg(feature = "json")]
+    pub fn to_json_ld(&self, subject: &str) -> serde_json::Value {
+        json_ld::to_json_ld(self, subject)
+    }
+}
+
+impl Xmp {
+    /// Export the RDF graph of the packet as N-Triples, with `subject`
+    /// as the IRI of the resource described. If `subject` is empty,
+    /// the resource is the blank node `_:b0`.
+    ///
+    /// Structs and arrays are blank nodes, the arrays being typed
+    /// `rdf:Bag`, `rdf:Seq` or `rdf:Alt` with the items as `rdf:_1`,
+    /// `rdf:_2`... The `xml:lang` qualifier is the language of the
+    /// literal. A node with other qualifiers is a blank node with the
+    /// value as `rdf:value`, like in RDF/XML.
+    ///
+    /// ```no_run
+    /// use exempi2::Xmp;
+    ///
+    /// let xmp = Xmp::new();
+    /// print!("{}", xmp.to_ntriples("http://example.com/assets/1234"));
+    /// // <http://example.com/assets/1234> <http://purl.org/dc/elements/1.1/title> _:b1 .
+    /// // _:b1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/1999/02/22-rdf-syntax-ns#Alt> .
+    /// // _:b1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#_1> "Hello"@x-default .
+    /// ```
+    pub fn to_ntriples(&self, subject: &str) -> String {
+        self.to_tree().to_ntriples(subject)
+    }
+
+    /// Export the RDF graph of the packet as Turtle, using the
+    /// registered prefixes. See `Xmp::to_ntriples()` for the graph.
+    pub fn to_turtle(&self, subject: &str) -> String {
+        self.to_tree().to_turtle(subject)
+    }
+
+    /// Export the RDF graph of the packet as JSON-LD. The `@context`
+    /// has the registered prefixes of the namespaces used. See
+    /// `Xmp::to_ntriples()` for the graph. Requires the `json` feature.
+    #[cfg(feature = "json")]
+    pub fn to_json_ld(&self, subject: &str) -> serde_json::Value {
+        self.to_tree().to_json_ld(subject)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::xmp::PropFlags;
+    use crate::xmptree::fixture::{alt_text, simple, structure, tree, NS};
+    use crate::xmptree::{XmpNode, XmpTree};
+
+    fn fixture() -> XmpTree {
+        tree(vec![
+            simple("test", "say \"foo\""),
+            alt_text("title", &[("x-default", "Hello")]),
+            structure(
+                "location",
+                vec![XmpNode::new(
+                    NS,
+                    "web",
+                    "http://example.com/",
+                    PropFlags::VALUE_IS_URI,
+                )],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_ntriples() {
+        let rdf = crate::NS_RDF_URI;
+        assert_eq!(
+            fixture().to_ntriples("urn:test"),
+            format!(
+                "<urn:test> <{ns}test> \"say \\\"foo\\\"\" .\n\
+                 <urn:test> <{ns}title> _:b1 .\n\
+                 <urn:test> <{ns}location> _:b2 .\n\
+                 _:b1 <{rdf}type> <{rdf}Alt> .\n\
+                 _:b1 <{rdf}_1> \"Hello\"@x-default .\n\
+                 _:b2 <{ns}web> <http://example.com/> .\n",
+                ns = NS,
+                rdf = rdf
+            )
+        );
+        assert!(fixture().to_ntriples("").starts_with("_:b0 "));
+    }
+
+    #[test]
+    fn test_turtle() {
+        let turtle = fixture().to_turtle("urn:test");
+        assert!(turtle.starts_with("@prefix rust: <http://rust.figuiere.net/ns/rust/> .\n"));
+        assert!(turtle.contains(
+            "<urn:test> rust:test \"say \\\"foo\\\"\" ;\n    rust:title _:b1 ;\n    rust:location _:b2 .\n"
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_ld() {
+        let json = fixture().to_json_ld("urn:test");
+        assert_eq!(json["@context"]["rust"], NS);
+        assert_eq!(json["@id"], "urn:test");
+        assert_eq!(json["rust:test"], "say \"foo\"");
+        assert_eq!(
+            json["rust:location"]["rust:web"],
+            serde_json::json!({"@id": "http://example.com/"})
+        );
+    }
+}
@@ -0,0 +1,1068 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+//! Serde data format to map Rust structs to and from an `Xmp` packet.
+//! Requires the `serde` feature.
+//!
+//! The struct fields are the properties. A field name is `prefix:name`,
+//! or just `name` to use the namespace of the struct: its serde name
+//! if it is a registered namespace uri or prefix, otherwise, for a
+//! nested struct, the namespace of the parent property. The prefixes
+//! must be registered or `BadSchema` is returned.
+//!
+//! - Strings, numbers, `bool` and unit enum variants are simple values.
+//! - `Option::None` is an absent property.
+//! - `Vec` and tuples are arrays, bag by default. Use the modules
+//!   [`bag`], [`seq`] and [`alt`] with `#[serde(with = "...")]` to
+//!   choose the form.
+//! - Maps of language to string, like `BTreeMap<String, String>`, are
+//!   alt-text arrays.
+//! - Nested structs are XMP structs.
+//!
+//! Enum variants with data, newtype, tuple or struct variants, have no
+//! XMP equivalent and return [`Error::Unsupported`]. So does anything
+//! but a struct at the top level. The serde errors, like a missing
+//! field or an invalid type, are [`Error::Message`] with their message.
+//!
+//! ```no_run
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! #[serde(rename = "dc")]
+//! struct Meta {
+//!     format: String,
+//!     #[serde(with = "exempi2::xmpserde::seq")]
+//!     creator: Vec<String>,
+//!     subject: Vec<String>,
+//!     #[serde(rename = "xmp:Rating")]
+//!     rating: Option<i32>,
+//! }
+//!
+//! let mut xmp = exempi2::Xmp::new();
+//! let meta = Meta {
+//!     format: "image/jpeg".to_owned(),
+//!     creator: vec!["Hubert".to_owned()],
+//!     subject: vec![],
+//!     rating: Some(5),
+//! };
+//! exempi2::to_xmp(&meta, &mut xmp).unwrap();
+//! let meta: Meta = exempi2::from_xmp(&xmp).unwrap();
+//! ```
+
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
+use serde::ser::{self, Serialize};
+
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmptree::{XmpNode, XmpNodeKind, XmpTree};
+use crate::NS_XML_URI;
+
+/// The newtype struct names used to pass the array form.
+const BAG: &str = "$exempi2::Bag";
+const SEQ: &str = "$exempi2::Seq";
+const ALT: &str = "$exempi2::Alt";
+
+/// Error of the serde data format. It converts to `exempi2::Error`,
+/// without the message.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Error from XMP, like `BadSchema` for a prefix that isn't
+    /// registered, or `BadValue` for a value that can't be parsed.
+    Xmp(crate::Error),
+    /// Error from serde, like a missing field, with its message.
+    Message(String),
+    /// The value has no XMP equivalent.
+    Unsupported(&'static str),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+impl From<c::XmpError> for Error {
+    fn from(err: c::XmpError) -> Error {
+        Error::Xmp(crate::Error::from(err))
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(err: crate::Error) -> Error {
+        Error::Xmp(err)
+    }
+}
+
+impl From<Error> for crate::Error {
+    fn from(err: Error) -> crate::Error {
+        match err {
+            Error::Xmp(err) => err,
+            Error::Message(_) => crate::Error::from(c::XmpError::BadValue),
+            Error::Unsupported(_) => crate::Error::from(c::XmpError::Unimplemented),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Xmp(err) => write!(f, "XMP error {:?}", err.0),
+            Error::Message(msg) => f.write_str(msg),
+            Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Message(msg.to_string())
+    }
+}
+
+/// The error for the enum variants with data.
+const VARIANT_WITH_DATA: Error = Error::Unsupported("enum variant with data");
+/// The error for anything but a struct at the top level.
+const NOT_A_STRUCT: Error = Error::Unsupported("only structs are supported at the top level");
+
+macro_rules! array_form_module {
+    ($module:ident, $name:expr, $doc:expr) => {
+        #[doc = $doc]
+        pub mod $module {
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+            /// Serialize the value as an array of this form.
+            pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                T: Serialize + ?Sized,
+                S: Serializer,
+            {
+                serializer.serialize_newtype_struct($name, value)
+            }
+
+            /// Deserialize the array. The form isn't checked.
+            pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+            where
+                T: Deserialize<'de>,
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+array_form_module!(bag, super::BAG, "Serialize a sequence as a bag array.");
+array_form_module!(seq, super::SEQ, "Serialize a sequence as a seq array.");
+array_form_module!(alt, super::ALT, "Serialize a sequence as an alt array.");
+
+/// Return the namespace named by a struct: a registered namespace
+/// uri or prefix.
+fn struct_namespace(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return None;
+    }
+    if crate::namespace_prefix(name).is_ok() {
+        return Some(name.to_owned());
+    }
+    crate::prefix_namespace(name)
+        .ok()
+        .map(|ns| String::from(&ns))
+}
+
+/// Return the namespace and the name of the field `key`.
+fn field_name(key: &str, ns: Option<&str>) -> Result<(String, String)> {
+    match key.split_once(':') {
+        Some((prefix, name)) => {
+            let ns =
+                crate::prefix_namespace(prefix).map_err(|_| Error::from(c::XmpError::BadSchema))?;
+            Ok((String::from(&ns), name.to_owned()))
+        }
+        None => ns
+            .map(|ns| (ns.to_owned(), key.to_owned()))
+            .ok_or_else(|| Error::from(c::XmpError::BadSchema)),
+    }
+}
+
+fn simple(value: String) -> XmpNode {
+    XmpNode {
+        ns: String::new(),
+        name: String::new(),
+        value,
+        flags: PropFlags::NONE,
+        kind: XmpNodeKind::Simple,
+        qualifiers: vec![],
+    }
+}
+
+/// Serialize a value to a node. `None` is serialized as no node.
+/// The namespace and name of the node are set by the caller.
+struct NodeSerializer<'a> {
+    /// The namespace of the unprefixed struct fields.
+    ns: Option<&'a str>,
+    /// The form of the next array.
+    form: Option<ArrayForm>,
+}
+
+impl<'a> NodeSerializer<'a> {
+    fn new(ns: Option<&'a str>) -> NodeSerializer<'a> {
+        NodeSerializer { ns, form: None }
+    }
+
+    fn simple(self, value: impl ToString) -> Result<Option<XmpNode>> {
+        Ok(Some(simple(value.to_string())))
+    }
+}
+
+/// Serialize an array.
+struct ArraySerializer<'a> {
+    ns: Option<&'a str>,
+    form: ArrayForm,
+    items: Vec<XmpNode>,
+}
+
+impl<'a> ArraySerializer<'a> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        if let Some(item) = value.serialize(NodeSerializer::new(self.ns))? {
+            self.items.push(item);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Option<XmpNode>> {
+        let mut node = simple(String::new());
+        node.flags = PropFlags::from(self.form);
+        node.kind = XmpNodeKind::Array(self.form, self.items);
+        Ok(Some(node))
+    }
+}
+
+impl<'a> ser::SerializeSeq for ArraySerializer<'a> {
+    type Ok = Option<XmpNode>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<XmpNode>> {
+        ArraySerializer::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTuple for ArraySerializer<'a> {
+    type Ok = Option<XmpNode>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<XmpNode>> {
+        ArraySerializer::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for ArraySerializer<'a> {
+    type Ok = Option<XmpNode>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<XmpNode>> {
+        ArraySerializer::end(self)
+    }
+}
+
+/// Serialize a map of language to text as an alt-text array.
+struct AltTextSerializer {
+    lang: Option<String>,
+    items: Vec<XmpNode>,
+}
+
+impl ser::SerializeMap for AltTextSerializer {
+    type Ok = Option<XmpNode>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(NodeSerializer::new(None))? {
+            Some(XmpNode {
+                kind: XmpNodeKind::Simple,
+                value,
+                ..
+            }) => {
+                self.lang = Some(value);
+                Ok(())
+            }
+            _ => Err(Error::from(c::XmpError::BadValue)),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let lang = self
+            .lang
+            .take()
+            .ok_or_else(|| Error::from(c::XmpError::BadParam))?;
+        match value.serialize(NodeSerializer::new(None))? {
+            Some(mut item) if item.kind == XmpNodeKind::Simple => {
+                let mut qualifier = simple(lang);
+                qualifier.ns = NS_XML_URI.to_owned();
+                qualifier.name = "lang".to_owned();
+                qualifier.flags = PropFlags::IS_QUALIFIER;
+                item.flags = PropFlags::HAS_QUALIFIERS | PropFlags::HAS_LANG;
+                item.qualifiers.push(qualifier);
+                self.items.push(item);
+                Ok(())
+            }
+            Some(_) => Err(Error::from(c::XmpError::BadValue)),
+            None => Ok(()),
+        }
+    }
+
+    fn end(mut self) -> Result<Option<XmpNode>> {
+        // x-default is always the first item.
+        self.items
+            .sort_by_key(|item| item.qualifiers[0].value != "x-default");
+        let mut node = simple(String::new());
+        node.flags = PropFlags::from(ArrayForm::AltText);
+        node.kind = XmpNodeKind::Array(ArrayForm::AltText, self.items);
+        Ok(Some(node))
+    }
+}
+
+/// Serialize a struct.
+struct StructSerializer {
+    ns: Option<String>,
+    fields: Vec<XmpNode>,
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Option<XmpNode>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let (ns, name) = field_name(key, self.ns.as_deref())?;
+        if let Some(mut field) = value.serialize(NodeSerializer::new(Some(&ns)))? {
+            field.ns = ns;
+            field.name = name;
+            self.fields.push(field);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Option<XmpNode>> {
+        let mut node = simple(String::new());
+        node.flags = PropFlags::VALUE_IS_STRUCT;
+        node.kind = XmpNodeKind::Struct(self.fields);
+        Ok(Some(node))
+    }
+}
+
+impl<'a> ser::Serializer for NodeSerializer<'a> {
+    type Ok = Option<XmpNode>;
+    type Error = Error;
+    type SerializeSeq = ArraySerializer<'a>;
+    type SerializeTuple = ArraySerializer<'a>;
+    type SerializeTupleStruct = ArraySerializer<'a>;
+    type SerializeTupleVariant = ser::Impossible<Option<XmpNode>, Error>;
+    type SerializeMap = AltTextSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = ser::Impossible<Option<XmpNode>, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Option<XmpNode>> {
+        self.simple(if v { "True" } else { "False" })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Option<XmpNode>> {
+        self.simple(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Option<XmpNode>> {
+        let v = std::str::from_utf8(v).map_err(|_| Error::from(c::XmpError::BadValue))?;
+        self.simple(v)
+    }
+
+    fn serialize_none(self) -> Result<Option<XmpNode>> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Option<XmpNode>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<XmpNode>> {
+        self.simple("")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<XmpNode>> {
+        self.simple("")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Option<XmpNode>> {
+        self.simple(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Option<XmpNode>> {
+        let form = match name {
+            BAG => Some(ArrayForm::Bag),
+            SEQ => Some(ArrayForm::Seq),
+            ALT => Some(ArrayForm::Alt),
+            _ => self.form,
+        };
+        value.serialize(NodeSerializer { ns: self.ns, form })
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Option<XmpNode>> {
+        Err(VARIANT_WITH_DATA)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ArraySerializer<'a>> {
+        Ok(ArraySerializer {
+            ns: self.ns,
+            form: self.form.unwrap_or(ArrayForm::Bag),
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ArraySerializer<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ArraySerializer<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(VARIANT_WITH_DATA)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<AltTextSerializer> {
+        Ok(AltTextSerializer {
+            lang: None,
+            items: vec![],
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<StructSerializer> {
+        Ok(StructSerializer {
+            ns: struct_namespace(name).or_else(|| self.ns.map(str::to_owned)),
+            fields: vec![],
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(VARIANT_WITH_DATA)
+    }
+}
+
+/// Serializer writing a struct to the properties of an `Xmp`.
+///
+/// Only structs can be serialized at the top level. The properties
+/// are replaced, and the ones for the `None` fields are deleted.
+pub struct Serializer<'a> {
+    xmp: &'a mut Xmp,
+    ns: Option<String>,
+}
+
+impl<'a> Serializer<'a> {
+    /// New serializer writing to `xmp`.
+    pub fn new(xmp: &'a mut Xmp) -> Serializer<'a> {
+        Serializer { xmp, ns: None }
+    }
+}
+
+impl<'a, 'b> ser::SerializeStruct for &'b mut Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let (ns, name) = field_name(key, self.ns.as_deref())?;
+        match value.serialize(NodeSerializer::new(Some(&ns)))? {
+            Some(node) => self.xmp.set_node(&ns, &name, &node)?,
+            None if self.xmp.has_property(&ns, &name) => self.xmp.delete_property(&ns, &name)?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Return `Error::Unsupported` for the values that can't be at the top
+/// level.
+macro_rules! reject_top_level {
+    ($($method:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ret> {
+                Err(NOT_A_STRUCT)
+            }
+        )*
+    };
+}
+
+impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<(), Error>;
+    type SerializeTuple = ser::Impossible<(), Error>;
+    type SerializeTupleStruct = ser::Impossible<(), Error>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    reject_top_level! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        Err(NOT_A_STRUCT)
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self> {
+        self.ns = struct_namespace(name);
+        Ok(self)
+    }
+}
+
+/// Deserialize a node.
+struct NodeDeserializer<'a> {
+    node: &'a XmpNode,
+    /// The namespace of the unprefixed struct fields.
+    ns: Option<&'a str>,
+}
+
+impl<'a> NodeDeserializer<'a> {
+    /// Parse the simple value.
+    fn parse<T: std::str::FromStr>(&self) -> Result<T> {
+        match self.node.kind {
+            XmpNodeKind::Simple => self
+                .node
+                .value
+                .trim()
+                .parse()
+                .map_err(|_| Error::from(c::XmpError::BadValue)),
+            _ => Err(Error::from(c::XmpError::BadValue)),
+        }
+    }
+
+    fn items(&self) -> Result<&'a [XmpNode]> {
+        match self.node.kind {
+            XmpNodeKind::Array(_, ref items) => Ok(items),
+            _ => Err(Error::from(c::XmpError::BadValue)),
+        }
+    }
+
+    /// The alt-text items as language and value.
+    fn alt_text(&self) -> Result<Vec<(String, &'a XmpNode)>> {
+        self.items()?
+            .iter()
+            .map(|item| {
+                item.qualifier(NS_XML_URI, "lang")
+                    .map(|lang| (lang.value.clone(), item))
+                    .ok_or_else(|| Error::from(c::XmpError::BadValue))
+            })
+            .collect()
+    }
+
+    /// The struct fields as `prefix:name`.
+    fn fields(&self) -> Result<Vec<(String, &'a XmpNode)>> {
+        match self.node.kind {
+            XmpNodeKind::Struct(ref fields) => Ok(fields
+                .iter()
+                .map(|field| {
+                    let prefix = crate::namespace_prefix(&field.ns)
+                        .map(|prefix| String::from(&prefix))
+                        .unwrap_or_default();
+                    (format!("{}{}", prefix, field.name), field)
+                })
+                .collect()),
+            _ => Err(Error::from(c::XmpError::BadValue)),
+        }
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for NodeDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.node.kind {
+            XmpNodeKind::Simple => visitor.visit_str(&self.node.value),
+            XmpNodeKind::Struct(_) => self.deserialize_map(visitor),
+            XmpNodeKind::Array(ArrayForm::AltText, _) => self.deserialize_map(visitor),
+            XmpNodeKind::Array(..) => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.node.value.trim() {
+            v if v.eq_ignore_ascii_case("true") || v == "1" => visitor.visit_bool(true),
+            v if v.eq_ignore_ascii_case("false") || v == "0" => visitor.visit_bool(false),
+            _ => Err(Error::from(c::XmpError::BadValue)),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_str(&self.node.value)
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.node.value.clone())
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bytes(self.node.value.as_bytes())
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.node.value.as_bytes().to_vec())
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // The node exists.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(NodeSeq {
+            items: self.items()?.iter(),
+            ns: self.ns,
+        })
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let entries = match self.node.kind {
+            XmpNodeKind::Struct(_) => self.fields()?,
+            _ => self.alt_text()?,
+        };
+        visitor.visit_map(NodeMap {
+            entries: entries.into_iter(),
+            value: None,
+            ns: self.ns,
+        })
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let children = match self.node.kind {
+            XmpNodeKind::Struct(ref children) => children,
+            _ => return Err(Error::from(c::XmpError::BadValue)),
+        };
+        let ns = struct_namespace(name).or_else(|| self.ns.map(str::to_owned));
+        let nodes: Vec<&XmpNode> = children.iter().collect();
+        visitor.visit_map(NodeMap {
+            entries: struct_entries(&nodes, fields, ns.as_deref())?.into_iter(),
+            value: None,
+            ns: None,
+        })
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.node.kind {
+            XmpNodeKind::Simple => visitor.visit_enum(UnitVariant(&self.node.value)),
+            _ => Err(VARIANT_WITH_DATA),
+        }
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// Access to a unit enum variant, named by the value.
+struct UnitVariant<'a>(&'a str);
+
+impl<'de, 'a> de::EnumAccess<'de> for UnitVariant<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let deserializer: de::value::StrDeserializer<Error> = self.0.into_deserializer();
+        Ok((seed.deserialize(deserializer)?, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for UnitVariant<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, _seed: T) -> Result<T::Value> {
+        Err(VARIANT_WITH_DATA)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, _len: usize, _visitor: V) -> Result<V::Value> {
+        Err(VARIANT_WITH_DATA)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value> {
+        Err(VARIANT_WITH_DATA)
+    }
+}
+
+/// Return the entries for the `fields` found in `nodes`, keyed by the
+/// field name.
+fn struct_entries<'a>(
+    nodes: &[&'a XmpNode],
+    fields: &[&str],
+    ns: Option<&str>,
+) -> Result<Vec<(String, &'a XmpNode)>> {
+    let mut entries = vec![];
+    for key in fields {
+        let (ns, name) = field_name(key, ns)?;
+        if let Some(node) = nodes.iter().find(|n| n.ns == ns && n.name == name) {
+            entries.push(((*key).to_owned(), *node));
+        }
+    }
+    Ok(entries)
+}
+
+/// Access to the array items.
+struct NodeSeq<'a> {
+    items: std::slice::Iter<'a, XmpNode>,
+    ns: Option<&'a str>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for NodeSeq<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        match self.items.next() {
+            Some(node) => seed
+                .deserialize(NodeDeserializer { node, ns: self.ns })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+/// Access to named nodes: struct fields, properties or alt-text items.
+struct NodeMap<'a> {
+    entries: std::vec::IntoIter<(String, &'a XmpNode)>,
+    value: Option<&'a XmpNode>,
+    /// The namespace for the nested structs. `None` to use the
+    /// namespace of each node.
+    ns: Option<&'a str>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for NodeMap<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((key, node)) => {
+                self.value = Some(node);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let node = self
+            .value
+            .take()
+            .ok_or_else(|| Error::from(c::XmpError::BadParam))?;
+        let ns = match node.ns.as_str() {
+            "" => self.ns,
+            ns => Some(ns),
+        };
+        seed.deserialize(NodeDeserializer { node, ns })
+    }
+}
+
+/// Deserializer reading a struct from the properties of an `Xmp`.
+///
+/// Only structs can be deserialized at the top level. The properties
+/// that aren't fields of the struct are ignored.
+pub struct Deserializer {
+    tree: XmpTree,
+}
+
+impl Deserializer {
+    /// New deserializer reading from `xmp`.
+    pub fn new(xmp: &Xmp) -> Deserializer {
+        Deserializer {
+            tree: xmp.to_tree(),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(NOT_A_STRUCT)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let ns = struct_namespace(name);
+        let nodes: Vec<&XmpNode> = self
+            .tree
+            .schemas
+            .iter()
+            .flat_map(|schema| &schema.properties)
+            .collect();
+        visitor.visit_map(NodeMap {
+            entries: struct_entries(&nodes, fields, ns.as_deref())?.into_iter(),
+            value: None,
+            ns: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Deserialize `T` from the properties of `xmp`. `T` must be a struct.
+/// See the [module documentation](self) for the data model.
+pub fn from_xmp<T: DeserializeOwned>(xmp: &Xmp) -> Result<T> {
+    T::deserialize(&Deserializer::new(xmp))
+}
+
+/// Serialize `value` to the properties of `xmp`. `value` must be a
+/// struct. See the [module documentation](self) for the data model.
+/// The change is atomic: on error the packet is left untouched.
+pub fn to_xmp<T: Serialize + ?Sized>(value: &T, xmp: &mut Xmp) -> Result<()> {
+    let mut copy = xmp.clone();
+    value.serialize(&mut Serializer::new(&mut copy))?;
+    std::mem::swap(xmp, &mut copy);
+    Ok(())
+}
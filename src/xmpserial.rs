@@ -0,0 +1,132 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::io;
+
+use crate::xmp::{SerialFlags, Xmp};
+use crate::xmpstring::XmpString;
+use crate::Result;
+
+/// The options to serialize a packet.
+///
+/// The newline and the tab are empty by default, meaning the XMP
+/// Toolkit default: `\n` and a space.
+///
+/// ```no_run
+/// use exempi2::{SerialFlags, SerialOptions, Xmp};
+///
+/// let xmp = Xmp::new();
+/// let options = SerialOptions::new()
+///     .flags(SerialFlags::OMITPACKETWRAPPER | SerialFlags::ENCODEUTF16BIG)
+///     .newline("\r\n")
+///     .canonical(true);
+/// let bytes = xmp.serialize_to_bytes(&options);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SerialOptions {
+    flags: SerialFlags,
+    padding: u32,
+    newline: String,
+    tab: String,
+    indent: i32,
+    canonical: bool,
+}
+
+impl SerialOptions {
+    /// New default options: UTF-8 with the packet wrapper and the
+    /// default padding.
+    pub fn new() -> SerialOptions {
+        SerialOptions::default()
+    }
+
+    /// Set the serialization flags, including the encoding.
+    pub fn flags(mut self, flags: SerialFlags) -> SerialOptions {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the padding. 0 is the default padding. With
+    /// `SerialFlags::EXACTPACKETLENGTH` this is the packet length.
+    pub fn padding(mut self, padding: u32) -> SerialOptions {
+        self.padding = padding;
+        self
+    }
+
+    /// Set the newline string.
+    pub fn newline(mut self, newline: &str) -> SerialOptions {
+        self.newline = newline.to_owned();
+        self
+    }
+
+    /// Set the string used for one level of indentation.
+    pub fn tab(mut self, tab: &str) -> SerialOptions {
+        self.tab = tab.to_owned();
+        self
+    }
+
+    /// Set the base indentation level.
+    pub fn indent(mut self, indent: i32) -> SerialOptions {
+        self.indent = indent;
+        self
+    }
+
+    /// Serialize the properties in canonical order. See
+    /// `Xmp::canonical()`.
+    pub fn canonical(mut self, canonical: bool) -> SerialOptions {
+        self.canonical = canonical;
+        self
+    }
+
+    fn serialize(&self, xmp: &Xmp) -> Result<XmpString> {
+        let canonical;
+        let xmp = if self.canonical {
+            canonical = xmp.canonical()?;
+            &canonical
+        } else {
+            xmp
+        };
+        xmp.serialize_and_format(
+            self.flags,
+            self.padding,
+            &self.newline,
+            &self.tab,
+            self.indent,
+        )
+    }
+}
+
+impl Xmp {
+    /// Serialize the packet to bytes, in the encoding set in the
+    /// options flags. Unlike `XmpString::to_str()` this is correct for
+    /// the UTF-16 and UTF-32 encodings.
+    pub fn serialize_to_bytes(&self, options: &SerialOptions) -> Result<Vec<u8>> {
+        options
+            .serialize(self)
+            .map(|buffer| buffer.as_bytes().to_vec())
+    }
+
+    /// Serialize the packet to `writer`, in the encoding set in the
+    /// options flags. The XMP errors are returned as `io::Error`.
+    ///
+    /// ```no_run
+    /// use exempi2::{SerialOptions, Xmp};
+    ///
+    /// let xmp = Xmp::new();
+    /// let file = std::fs::File::create("image.xmp").unwrap();
+    /// xmp.serialize_to_writer(&SerialOptions::new(), file).unwrap();
+    /// ```
+    pub fn serialize_to_writer(
+        &self,
+        options: &SerialOptions,
+        mut writer: impl io::Write,
+    ) -> io::Result<()> {
+        let buffer = options
+            .serialize(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        writer.write_all(buffer.as_bytes())
+    }
+}
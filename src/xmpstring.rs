@@ -5,7 +5,6 @@
 // See top-level LICENSE file.
 //
 
-use std::ffi::CStr;
 use std::fmt;
 use std::str;
 
@@ -19,13 +18,13 @@ use std::str;
 /// XmpString support several of the standard traits.
 ///
 /// ```no_run
-/// use exempi2::{Xmp,PropFlags};
+/// use exempi2::Xmp;
 ///
 /// let xmp = Xmp::new();
-/// let mut flags = PropFlags::empty();
 ///
-/// if let Ok(ref xmpstring) = xmp.get_property("http://rust.figuiere.net/ns/rust/", "rust", &mut flags) {
-///    println!("property value is {}, flags {}", xmpstring, flags.bits());
+/// if let Ok(Some(ref property)) = xmp.get_property("http://rust.figuiere.net/ns/rust/", "rust") {
+///    let xmpstring = &property.value;
+///    println!("property value is {}, flags {}", xmpstring, property.flags.bits());
 ///    println!("string len: {}", xmpstring.len());
 ///    let s = String::from(xmpstring);
 ///    println!("converted to std::String: {}", s);
@@ -74,26 +73,36 @@ impl XmpString {
         self.len() == 0
     }
 
-    /// Convert to a str
-    pub fn to_str(&self) -> Result<&str, std::str::Utf8Error> {
+    /// Return the bytes of the string, all of them. Unlike `to_str()`
+    /// it doesn't stop at the first NUL, so it is correct for the
+    /// UTF-16 and UTF-32 serializations.
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.0.is_null() {
+            return &[];
+        }
         unsafe {
-            let s = CStr::from_ptr(c::xmp_string_cstr(self.0));
-            // we are supposed to receive UTF8 from the library.
-            str::from_utf8(s.to_bytes())
+            let s = c::xmp_string_cstr(self.0);
+            if s.is_null() {
+                return &[];
+            }
+            std::slice::from_raw_parts(s as *const u8, self.len())
         }
     }
+
+    /// Convert to a str
+    pub fn to_str(&self) -> Result<&str, std::str::Utf8Error> {
+        // we are supposed to receive UTF8 from the library.
+        str::from_utf8(self.as_bytes())
+    }
 }
 
 impl From<&XmpString> for String {
     /// Convert an XmpString safely to string.
     /// The result is lossy in it is not utf-8.
     fn from(s: &XmpString) -> String {
-        unsafe {
-            let s = CStr::from_ptr(c::xmp_string_cstr(s.0));
-            // we are supposed to receive UTF8 from the library.
-            // be we'll play it safe.
-            String::from_utf8_lossy(s.to_bytes()).to_string()
-        }
+        // we are supposed to receive UTF8 from the library.
+        // be we'll play it safe.
+        String::from_utf8_lossy(s.as_bytes()).to_string()
     }
 }
 
@@ -109,10 +118,7 @@ impl Drop for XmpString {
 impl Eq for XmpString {}
 impl PartialEq for XmpString {
     fn eq(&self, other: &XmpString) -> bool {
-        unsafe {
-            CStr::from_ptr(c::xmp_string_cstr(self.0))
-                == CStr::from_ptr(c::xmp_string_cstr(other.0))
-        }
+        self.as_bytes() == other.as_bytes()
     }
 }
 
@@ -0,0 +1,543 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use crate::error::Error;
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmpiterator::{IterFlags, XmpIterator};
+use crate::xmppath::{ToXmpPath, XmpPath};
+use crate::Result;
+
+/// The kind of node, with the children.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum XmpNodeKind {
+    /// A simple value.
+    Simple,
+    /// A struct, with the fields.
+    Struct(Vec<XmpNode>),
+    /// An array, with the items.
+    Array(ArrayForm, Vec<XmpNode>),
+}
+
+/// A node of the tree: a property, a struct field, an array item
+/// or a qualifier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct XmpNode {
+    /// The namespace uri. Empty for array items.
+    pub ns: String,
+    /// The name, without prefix. Empty for array items.
+    pub name: String,
+    /// The value. Empty for structs and arrays.
+    pub value: String,
+    /// The property flags.
+    pub flags: PropFlags,
+    /// The kind of node.
+    pub kind: XmpNodeKind,
+    /// The qualifiers.
+    pub qualifiers: Vec<XmpNode>,
+}
+
+impl XmpNode {
+    /// New node. The kind is set from the flags, without children.
+    pub fn new(ns: &str, name: &str, value: &str, flags: PropFlags) -> XmpNode {
+        let kind = if flags.contains(PropFlags::VALUE_IS_STRUCT) {
+            XmpNodeKind::Struct(vec![])
+        } else if let Some(form) = ArrayForm::from_flags(flags) {
+            XmpNodeKind::Array(form, vec![])
+        } else {
+            XmpNodeKind::Simple
+        };
+        XmpNode {
+            ns: ns.to_owned(),
+            name: name.to_owned(),
+            value: value.to_owned(),
+            flags,
+            kind,
+            qualifiers: vec![],
+        }
+    }
+
+    /// The fields if the node is a struct, or the items if it is an array.
+    pub fn children(&self) -> &[XmpNode] {
+        match self.kind {
+            XmpNodeKind::Simple => &[],
+            XmpNodeKind::Struct(ref fields) => fields,
+            XmpNodeKind::Array(_, ref items) => items,
+        }
+    }
+
+    /// The form of the array, `None` if it isn't an array.
+    pub fn array_form(&self) -> Option<ArrayForm> {
+        match self.kind {
+            XmpNodeKind::Array(form, _) => Some(form),
+            _ => None,
+        }
+    }
+
+    /// Find the struct field `name` from the namespace `ns`.
+    pub fn field(&self, ns: &str, name: &str) -> Option<&XmpNode> {
+        match self.kind {
+            XmpNodeKind::Struct(ref fields) => find_node(fields, ns, name),
+            _ => None,
+        }
+    }
+
+    /// Find the qualifier `name` from the namespace `ns`.
+    pub fn qualifier(&self, ns: &str, name: &str) -> Option<&XmpNode> {
+        find_node(&self.qualifiers, ns, name)
+    }
+
+    /// Add a child: a qualifier, a struct field or an array item
+    /// depending on the flags and on the kind of node.
+    fn add_child(&mut self, child: XmpNode) {
+        if child.flags.contains(PropFlags::IS_QUALIFIER) {
+            self.qualifiers.push(child);
+            return;
+        }
+        match self.kind {
+            XmpNodeKind::Simple => {}
+            XmpNodeKind::Struct(ref mut fields) => fields.push(child),
+            XmpNodeKind::Array(_, ref mut items) => items.push(child),
+        }
+    }
+}
+
+/// The properties of a schema.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct XmpSchema {
+    /// The namespace uri.
+    pub uri: String,
+    /// The prefix, without the colon.
+    pub prefix: String,
+    /// The top-level properties.
+    pub properties: Vec<XmpNode>,
+}
+
+impl XmpSchema {
+    /// New empty schema for `uri` using `prefix`.
+    pub fn new(uri: &str, prefix: &str) -> XmpSchema {
+        XmpSchema {
+            uri: uri.to_owned(),
+            prefix: prefix.to_owned(),
+            properties: vec![],
+        }
+    }
+
+    /// Find the property `name`.
+    pub fn property(&self, name: &str) -> Option<&XmpNode> {
+        find_node(&self.properties, &self.uri, name)
+    }
+}
+
+/// An owned tree of the properties of an `Xmp` packet.
+///
+/// ```no_run
+/// use exempi2::{Xmp, XmpNodeKind};
+///
+/// let xmp = Xmp::new();
+/// let tree = xmp.to_tree();
+/// for schema in &tree.schemas {
+///     for property in &schema.properties {
+///         let name = format!("{}:{}", schema.prefix, property.name);
+///         match property.kind {
+///             XmpNodeKind::Simple => println!("{} = {}", name, property.value),
+///             _ => println!("{} has {} children", name, property.children().len()),
+///         }
+///     }
+/// }
+/// let copy = Xmp::from_tree(&tree);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct XmpTree {
+    /// The schemas, in the order of the packet.
+    pub schemas: Vec<XmpSchema>,
+}
+
+impl XmpTree {
+    /// Find the schema for `uri`.
+    pub fn schema(&self, uri: &str) -> Option<&XmpSchema> {
+        self.schemas.iter().find(|schema| schema.uri == uri)
+    }
+
+    /// Return the prefix of `ns`, without the colon. The prefixes of the
+    /// schemas in the tree are used first, then the registered ones.
+    pub(crate) fn prefix_for(&self, ns: &str) -> Option<String> {
+        self.schema(ns)
+            .map(|schema| schema.prefix.clone())
+            .filter(|prefix| !prefix.is_empty())
+            .or_else(|| {
+                crate::namespace_prefix(ns)
+                    .ok()
+                    .map(|prefix| String::from(&prefix).trim_end_matches(':').to_owned())
+            })
+            .filter(|prefix| !prefix.is_empty())
+    }
+
+    /// Return `prefix:name`, or just `name` if the prefix of `ns` isn't
+    /// known.
+    pub(crate) fn qualified_name(&self, ns: &str, name: &str) -> String {
+        match self.prefix_for(ns) {
+            Some(prefix) => format!("{}:{}", prefix, name),
+            None => name.to_owned(),
+        }
+    }
+}
+
+fn find_node<'a>(nodes: &'a [XmpNode], ns: &str, name: &str) -> Option<&'a XmpNode> {
+    nodes.iter().find(|node| node.ns == ns && node.name == name)
+}
+
+/// Whether `path` is directly below `parent`. The iterator
+/// give the children right after the parent so it is enough to check
+/// the next step.
+fn is_child_path(parent: &str, path: &str) -> bool {
+    path.len() > parent.len()
+        && path.starts_with(parent)
+        && matches!(path.as_bytes()[parent.len()], b'/' | b'[')
+}
+
+/// Return the namespace and the name from a `prefix:name`.
+fn split_qualified_name(qname: &str) -> (String, String) {
+    match qname.split_once(':') {
+        Some((prefix, name)) => {
+            let ns = crate::prefix_namespace(prefix)
+                .map(|ns| String::from(&ns))
+                .unwrap_or_default();
+            (ns, name.to_owned())
+        }
+        None => (String::new(), qname.to_owned()),
+    }
+}
+
+/// Return the namespace and the name for the last step of a path.
+/// Array items have neither.
+fn step_name(step: &str) -> (String, String) {
+    if let Some(qname) = step.strip_prefix("/?") {
+        split_qualified_name(qname)
+    } else if let Some(qname) = step.strip_prefix('/') {
+        split_qualified_name(qname)
+    } else {
+        (String::new(), String::new())
+    }
+}
+
+/// Return the last step of a path from the iterator.
+fn last_step(path: &str) -> &str {
+    match path.rfind(['/', '[']) {
+        Some(pos) => &path[pos..],
+        None => path,
+    }
+}
+
+/// The nodes being built from the iterator, with their path.
+/// The iterator give the nodes depth first.
+#[derive(Default)]
+struct NodeStack(Vec<(String, XmpNode)>);
+
+impl NodeStack {
+    /// The path of the node at the top.
+    fn top_path(&self) -> Option<&str> {
+        self.0.last().map(|(path, _)| path.as_str())
+    }
+
+    fn push(&mut self, path: String, node: XmpNode) {
+        self.0.push((path, node));
+    }
+
+    /// Pop the nodes until the top is the parent of `path`, adding each
+    /// one to its parent. Return the root node if it was popped.
+    fn unwind_to(&mut self, path: &str) -> Option<XmpNode> {
+        while let Some(parent) = self.top_path() {
+            if is_child_path(parent, path) {
+                break;
+            }
+            let (_, node) = self.0.pop()?;
+            match self.0.last_mut() {
+                Some((_, parent)) => parent.add_child(node),
+                None => return Some(node),
+            }
+        }
+        None
+    }
+
+    /// Pop all the nodes, and return the root node.
+    fn finish(&mut self) -> Option<XmpNode> {
+        self.unwind_to("")
+    }
+}
+
+/// Register the namespace `uri` with `prefix`, unless it is already
+/// registered or the prefix is empty.
+pub(crate) fn register_if_unknown(uri: &str, prefix: &str) -> Result<()> {
+    if !prefix.is_empty() && crate::namespace_prefix(uri).is_err() {
+        crate::register_namespace(uri, prefix)?;
+    }
+    Ok(())
+}
+
+/// Write the node and its children at `path`.
+fn write_node(xmp: &mut Xmp, schema: &str, path: XmpPath, node: &XmpNode) -> Result<()> {
+    match node.kind {
+        XmpNodeKind::Simple => xmp.set_property(
+            schema,
+            &path,
+            &node.value,
+            node.flags & PropFlags::VALUE_IS_URI,
+        )?,
+        XmpNodeKind::Struct(ref fields) => {
+            xmp.set_property(schema, &path, "", PropFlags::VALUE_IS_STRUCT)?;
+            for field in fields {
+                write_node(
+                    xmp,
+                    schema,
+                    path.clone().field(&field.ns, &field.name),
+                    field,
+                )?;
+            }
+        }
+        XmpNodeKind::Array(form, ref items) => {
+            xmp.set_property(schema, &path, "", PropFlags::from(form))?;
+            // Setting the item after the last one append it.
+            for (index, item) in items.iter().enumerate() {
+                write_node(xmp, schema, path.clone().index(index as i32 + 1), item)?;
+            }
+        }
+    }
+    for qualifier in &node.qualifiers {
+        write_node(
+            xmp,
+            schema,
+            path.clone().qualifier(&qualifier.ns, &qualifier.name),
+            qualifier,
+        )?;
+    }
+    Ok(())
+}
+
+impl Xmp {
+    /// Build an owned tree of the properties. Aliases aren't included.
+    pub fn to_tree(&self) -> XmpTree {
+        let mut tree = XmpTree::default();
+        let mut stack = NodeStack::default();
+        for value in XmpIterator::with_path(self, "", "", IterFlags::PROPERTIES) {
+            let uri = String::from(&value.schema);
+            let path = String::from(&value.name);
+            if let Some(node) = stack.unwind_to(&path) {
+                if let Some(schema) = tree.schemas.last_mut() {
+                    schema.properties.push(node);
+                }
+            }
+            let (ns, name) = match stack.top_path() {
+                Some(parent) => step_name(&path[parent.len()..]),
+                None => {
+                    if tree.schemas.last().map(|schema| &schema.uri) != Some(&uri) {
+                        let prefix = crate::namespace_prefix(&uri)
+                            .map(|prefix| String::from(&prefix))
+                            .unwrap_or_default();
+                        let prefix = prefix.trim_end_matches(':');
+                        tree.schemas.push(XmpSchema::new(&uri, prefix));
+                    }
+                    // The schema node itself.
+                    if path.is_empty() {
+                        continue;
+                    }
+                    let name = path.split_once(':').map_or(&path[..], |(_, name)| name);
+                    (uri.clone(), name.to_owned())
+                }
+            };
+            let node = XmpNode::new(&ns, &name, &String::from(&value.value), value.option);
+            stack.push(path, node);
+        }
+        if let Some(node) = stack.finish() {
+            if let Some(schema) = tree.schemas.last_mut() {
+                schema.properties.push(node);
+            }
+        }
+        tree
+    }
+
+    /// Get the property `name` with its children as a node.
+    /// Return `Ok(None)` if the property doesn't exist.
+    pub fn get_node<N: ToXmpPath>(&self, schema: &str, name: N) -> Result<Option<XmpNode>> {
+        let name = name.to_xmp_path()?;
+        if !self.has_property(schema, &name) {
+            return Ok(None);
+        }
+        let mut stack = NodeStack::default();
+        // The first node is the property itself.
+        for value in XmpIterator::with_path(self, schema, &name, IterFlags::PROPERTIES) {
+            let path = String::from(&value.name);
+            // Past the subtree.
+            if let Some(node) = stack.unwind_to(&path) {
+                return Ok(Some(node));
+            }
+            let (ns, name) = match stack.top_path() {
+                Some(parent) => step_name(&path[parent.len()..]),
+                // A top-level property.
+                None if last_step(&path) == path => {
+                    let name = path.split_once(':').map_or(&path[..], |(_, name)| name);
+                    (schema.to_owned(), name.to_owned())
+                }
+                None => step_name(last_step(&path)),
+            };
+            let node = XmpNode::new(&ns, &name, &String::from(&value.value), value.option);
+            stack.push(path, node);
+        }
+        Ok(stack.finish())
+    }
+
+    /// Replace the property `name` with the node and its children.
+    /// The name of the node is ignored.
+    pub fn set_node<N: ToXmpPath>(&mut self, schema: &str, name: N, node: &XmpNode) -> Result<()> {
+        let path = XmpPath::new(&name.to_xmp_path()?);
+        if self.has_property(schema, &path) {
+            self.delete_property(schema, &path)?;
+        }
+        write_node(self, schema, path, node)
+    }
+
+    /// Copy the property `src_path` from `source`, with all its children
+    /// and qualifiers, to `dst_path`. The destination is replaced.
+    /// Return `BadXPath` if the source property doesn't exist.
+    /// The change is atomic: on error the packet is left untouched.
+    ///
+    /// ```no_run
+    /// use exempi2::Xmp;
+    ///
+    /// let iptc = "http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/";
+    /// let template = Xmp::new();
+    /// let mut xmp = Xmp::new();
+    /// xmp.copy_subtree(&template, iptc, "CreatorContactInfo", iptc, "CreatorContactInfo")
+    ///     .unwrap();
+    /// ```
+    pub fn copy_subtree<S: ToXmpPath, D: ToXmpPath>(
+        &mut self,
+        source: &Xmp,
+        src_schema: &str,
+        src_path: S,
+        dst_schema: &str,
+        dst_path: D,
+    ) -> Result<()> {
+        let node = source
+            .get_node(src_schema, src_path)?
+            .ok_or_else(|| Error::from(c::XmpError::BadXPath))?;
+        let mut xmp = self.clone();
+        xmp.set_node(dst_schema, dst_path, &node)?;
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+
+    /// Build a packet from the tree. The namespaces of the fields and
+    /// qualifiers must be registered or `BadSchema` is returned.
+    ///
+    /// The schemas that aren't registered yet are registered with their
+    /// prefix. This changes the process-wide registry. The registered
+    /// namespaces are left as is.
+    pub fn from_tree(tree: &XmpTree) -> Result<Xmp> {
+        let mut xmp = Xmp::new();
+        for schema in &tree.schemas {
+            register_if_unknown(&schema.uri, &schema.prefix)?;
+            for property in &schema.properties {
+                write_node(
+                    &mut xmp,
+                    &schema.uri,
+                    XmpPath::new(&property.name),
+                    property,
+                )?;
+            }
+        }
+        Ok(xmp)
+    }
+}
+
+/// Builders for the trees used by the tests.
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::{XmpNode, XmpNodeKind, XmpSchema, XmpTree};
+    use crate::xmp::{ArrayForm, PropFlags};
+
+    /// The namespace of the tests, with the prefix `rust`.
+    pub(crate) const NS: &str = "http://rust.figuiere.net/ns/rust/";
+
+    /// A simple property of `NS`.
+    pub(crate) fn simple(name: &str, value: &str) -> XmpNode {
+        XmpNode::new(NS, name, value, PropFlags::NONE)
+    }
+
+    /// A qualifier of `NS`.
+    pub(crate) fn qualifier(name: &str, value: &str) -> XmpNode {
+        XmpNode::new(NS, name, value, PropFlags::IS_QUALIFIER)
+    }
+
+    /// Return `node` with the qualifiers added.
+    pub(crate) fn qualified(mut node: XmpNode, qualifiers: Vec<XmpNode>) -> XmpNode {
+        node.flags |= PropFlags::HAS_QUALIFIERS;
+        node.qualifiers.extend(qualifiers);
+        node
+    }
+
+    /// An array property of `NS` with simple items.
+    pub(crate) fn array(name: &str, form: ArrayForm, items: &[&str]) -> XmpNode {
+        let mut node = XmpNode::new(NS, name, "", PropFlags::from(form));
+        node.kind = XmpNodeKind::Array(
+            form,
+            items
+                .iter()
+                .map(|item| XmpNode::new("", "", item, PropFlags::NONE))
+                .collect(),
+        );
+        node
+    }
+
+    /// An alt-text property of `NS`, with the items as language and
+    /// value.
+    pub(crate) fn alt_text(name: &str, items: &[(&str, &str)]) -> XmpNode {
+        let mut node = XmpNode::new(NS, name, "", PropFlags::from(ArrayForm::AltText));
+        node.kind = XmpNodeKind::Array(
+            ArrayForm::AltText,
+            items
+                .iter()
+                .map(|(lang, value)| {
+                    let mut item = XmpNode::new(
+                        "",
+                        "",
+                        value,
+                        PropFlags::HAS_QUALIFIERS | PropFlags::HAS_LANG,
+                    );
+                    item.qualifiers.push(XmpNode::new(
+                        crate::NS_XML_URI,
+                        "lang",
+                        lang,
+                        PropFlags::IS_QUALIFIER,
+                    ));
+                    item
+                })
+                .collect(),
+        );
+        node
+    }
+
+    /// A struct property of `NS`.
+    pub(crate) fn structure(name: &str, fields: Vec<XmpNode>) -> XmpNode {
+        let mut node = XmpNode::new(NS, name, "", PropFlags::VALUE_IS_STRUCT);
+        node.kind = XmpNodeKind::Struct(fields);
+        node
+    }
+
+    /// A schema with the properties.
+    pub(crate) fn schema(uri: &str, prefix: &str, properties: Vec<XmpNode>) -> XmpSchema {
+        let mut schema = XmpSchema::new(uri, prefix);
+        schema.properties = properties;
+        schema
+    }
+
+    /// A tree with the properties in the schema `NS`.
+    pub(crate) fn tree(properties: Vec<XmpNode>) -> XmpTree {
+        XmpTree {
+            schemas: vec![schema(NS, "rust", properties)],
+        }
+    }
+}
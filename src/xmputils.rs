@@ -0,0 +1,372 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use crate::error::Error;
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmppath::ToXmpPath;
+use crate::xmptree::{XmpNode, XmpNodeKind};
+use crate::Result;
+
+bitflags! {
+    /// Options to catenate and separate the array items.
+    #[derive(Clone, Copy, Default, Debug, Eq, Hash, PartialEq)]
+    pub struct ArrayItemsFlags: u32 {
+        /// Commas are separators.
+        const NONE = 0;
+        /// Commas aren't separators.
+        const ALLOW_COMMAS = 0x1000_0000u32;
+    }
+}
+
+/// The kind of character, for catenating and separating.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CharKind {
+    Normal,
+    Space,
+    Comma,
+    Semicolon,
+    Quote,
+    Control,
+}
+
+fn classify(c: char) -> CharKind {
+    match c {
+        ' ' | '\u{3000}' | '\u{303F}' | '\u{2000}'..='\u{200B}' => CharKind::Space,
+        ',' | '\u{FF0C}' | '\u{FF64}' | '\u{FE50}' | '\u{FE51}' | '\u{3001}' | '\u{060C}'
+        | '\u{055D}' => CharKind::Comma,
+        ';' | '\u{FF1B}' | '\u{FE54}' | '\u{061B}' | '\u{037E}' => CharKind::Semicolon,
+        '"'
+        | '\u{00AB}'
+        | '\u{00BB}'
+        | '\u{2015}'
+        | '\u{2018}'..='\u{201F}'
+        | '\u{2039}'
+        | '\u{203A}'
+        | '\u{300C}'..='\u{300F}'
+        | '\u{301D}'..='\u{301F}' => CharKind::Quote,
+        c if c.is_control() || ('\u{2028}'..='\u{2029}').contains(&c) => CharKind::Control,
+        _ => CharKind::Normal,
+    }
+}
+
+/// Return the closing quote for the opening quote. Quotes that don't
+/// come in pair close themselves.
+fn closing_quote(open: char) -> char {
+    match open {
+        '\u{00AB}' => '\u{00BB}',
+        '\u{00BB}' => '\u{00AB}',
+        '\u{2018}' => '\u{2019}',
+        '\u{201A}' => '\u{201B}',
+        '\u{201C}' => '\u{201D}',
+        '\u{201E}' => '\u{201F}',
+        '\u{2039}' => '\u{203A}',
+        '\u{203A}' => '\u{2039}',
+        '\u{300C}' => '\u{300D}',
+        '\u{300E}' => '\u{300F}',
+        '\u{301D}' => '\u{301F}',
+        c => c,
+    }
+}
+
+/// Whether `c` close the quote. U+301D can be closed by U+301E or U+301F.
+fn is_closing_quote(c: char, open: char, close: char) -> bool {
+    c == close || (open == '\u{301D}' && c == '\u{301E}')
+}
+
+/// Quote the item if it would be split, doubling the closing quotes inside.
+fn apply_quotes(item: &str, open: char, close: char, allow_commas: bool) -> String {
+    let mut needs_quotes = item.chars().next().map(classify) == Some(CharKind::Quote);
+    let mut prev_space = false;
+    for c in item.chars() {
+        match classify(c) {
+            CharKind::Space if prev_space => needs_quotes = true,
+            CharKind::Space => prev_space = true,
+            kind => {
+                prev_space = false;
+                needs_quotes |= kind == CharKind::Semicolon
+                    || kind == CharKind::Control
+                    || (kind == CharKind::Comma && !allow_commas);
+            }
+        }
+    }
+    if !needs_quotes {
+        return item.to_owned();
+    }
+    let mut quoted = String::with_capacity(item.len() + 2);
+    quoted.push(open);
+    for c in item.chars() {
+        quoted.push(c);
+        if is_closing_quote(c, open, close) {
+            quoted.push(c);
+        }
+    }
+    quoted.push(close);
+    quoted
+}
+
+/// Catenate the items, quoting them with `quotes` if needed.
+fn catenate(items: &[&str], separator: &str, quotes: &str, allow_commas: bool) -> Result<String> {
+    let bad_param = || Error::from(c::XmpError::BadParam);
+
+    let separator = if separator.is_empty() {
+        "; "
+    } else {
+        separator
+    };
+    // One semicolon and spaces only.
+    let kinds: Vec<CharKind> = separator.chars().map(classify).collect();
+    if kinds.iter().filter(|k| **k == CharKind::Semicolon).count() != 1
+        || kinds
+            .iter()
+            .any(|k| *k != CharKind::Semicolon && *k != CharKind::Space)
+    {
+        return Err(bad_param());
+    }
+
+    let quotes: Vec<char> = if quotes.is_empty() {
+        vec!['"']
+    } else {
+        quotes.chars().collect()
+    };
+    let (open, close) = match quotes[..] {
+        [open] => (open, open),
+        [open, close] if close == closing_quote(open) => (open, close),
+        _ => return Err(bad_param()),
+    };
+    if classify(open) != CharKind::Quote || classify(close) != CharKind::Quote {
+        return Err(bad_param());
+    }
+
+    Ok(items
+        .iter()
+        .map(|item| apply_quotes(item, open, close, allow_commas))
+        .collect::<Vec<String>>()
+        .join(separator))
+}
+
+/// Separate the items. This is the reverse of `catenate()`.
+fn separate(text: &str, allow_commas: bool) -> Vec<String> {
+    let is_item_char = |kind: CharKind| {
+        kind == CharKind::Normal
+            || kind == CharKind::Quote
+            || (kind == CharKind::Comma && allow_commas)
+    };
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut items = vec![];
+    let mut pos = 0;
+    loop {
+        // Skip the separators before the item.
+        while pos < chars.len()
+            && !matches!(classify(chars[pos]), CharKind::Normal | CharKind::Quote)
+        {
+            pos += 1;
+        }
+        if pos >= chars.len() {
+            break;
+        }
+        let mut item = String::new();
+        if classify(chars[pos]) != CharKind::Quote {
+            // Up to a separator, multiple spaces or a space followed by
+            // a separator.
+            while pos < chars.len() {
+                let kind = classify(chars[pos]);
+                if !is_item_char(kind)
+                    && (kind != CharKind::Space
+                        || pos + 1 >= chars.len()
+                        || !is_item_char(classify(chars[pos + 1])))
+                {
+                    break;
+                }
+                item.push(chars[pos]);
+                pos += 1;
+            }
+        } else {
+            // Up to the closing quote, undoubling the quotes.
+            let open = chars[pos];
+            let close = closing_quote(open);
+            pos += 1;
+            while pos < chars.len() {
+                let c = chars[pos];
+                pos += 1;
+                let is_quote = classify(c) == CharKind::Quote
+                    && (c == open || is_closing_quote(c, open, close));
+                if !is_quote {
+                    item.push(c);
+                } else if chars.get(pos) == Some(&c) {
+                    // Doubled.
+                    item.push(c);
+                    pos += 1;
+                } else if !is_closing_quote(c, open, close) {
+                    item.push(c);
+                } else {
+                    break;
+                }
+            }
+        }
+        items.push(item);
+    }
+    items
+}
+
+impl Xmp {
+    /// Catenate the items of the array into a string, with `separator`
+    /// between the items. The items that would be split by
+    /// `separate_array_items()` are quoted with `quotes`.
+    ///
+    /// `separator` must have one semicolon and spaces, and is `"; "` if
+    /// empty. `quotes` is either one quote character, or an opening and
+    /// a closing quote, and is `"` if empty. `ArrayItemsFlags::ALLOW_COMMAS`
+    /// in `options` allow commas in the items without quoting.
+    ///
+    /// Return an empty string if the array doesn't exist, `BadParam` if the
+    /// property isn't a bag or seq array of simple items.
+    ///
+    /// ```no_run
+    /// use exempi2::{ArrayItemsFlags, Xmp};
+    ///
+    /// let dc = "http://purl.org/dc/elements/1.1/";
+    /// let xmp = Xmp::new();
+    /// let keywords = xmp
+    ///     .catenate_array_items(dc, "subject", "; ", "\"", ArrayItemsFlags::NONE)
+    ///     .unwrap();
+    /// ```
+    pub fn catenate_array_items<N: ToXmpPath>(
+        &self,
+        schema: &str,
+        name: N,
+        separator: &str,
+        quotes: &str,
+        options: ArrayItemsFlags,
+    ) -> Result<String> {
+        let node = match self.get_node(schema, name)? {
+            Some(node) => node,
+            None => return Ok(String::new()),
+        };
+        let items = match node.kind {
+            XmpNodeKind::Array(ArrayForm::Bag, ref items)
+            | XmpNodeKind::Array(ArrayForm::Seq, ref items) => items,
+            _ => return Err(Error::from(c::XmpError::BadParam)),
+        };
+        if items.iter().any(|item| item.kind != XmpNodeKind::Simple) {
+            return Err(Error::from(c::XmpError::BadParam));
+        }
+        let items: Vec<&str> = items.iter().map(|item| item.value.as_str()).collect();
+        catenate(
+            &items,
+            separator,
+            quotes,
+            options.contains(ArrayItemsFlags::ALLOW_COMMAS),
+        )
+    }
+
+    /// Replace the items of the array with the ones separated from `text`.
+    /// This is the reverse of `catenate_array_items()`. The items that
+    /// were already in the array are kept with their qualifiers.
+    ///
+    /// The array form is from `array_options`: if the array doesn't
+    /// exist it is created with it, defaulting to a bag. If it exists,
+    /// the form must match or `BadXPath` is returned. Alt-text arrays
+    /// return `BadParam`. `ArrayItemsFlags::ALLOW_COMMAS` in `options`
+    /// keep commas in the items.
+    ///
+    /// The change is atomic: on error the packet is left untouched.
+    pub fn separate_array_items<N: ToXmpPath>(
+        &mut self,
+        schema: &str,
+        name: N,
+        array_options: PropFlags,
+        options: ArrayItemsFlags,
+        text: &str,
+    ) -> Result<()> {
+        let name = name.to_xmp_path()?;
+        let form = ArrayForm::from_flags(array_options);
+        if form == Some(ArrayForm::AltText) {
+            return Err(Error::from(c::XmpError::BadParam));
+        }
+        let old_items = match self.get_node(schema, &name)? {
+            Some(node) => match node.kind {
+                XmpNodeKind::Array(ArrayForm::AltText, _) => {
+                    return Err(Error::from(c::XmpError::BadParam))
+                }
+                XmpNodeKind::Array(old_form, _) if form.is_some() && form != Some(old_form) => {
+                    return Err(Error::from(c::XmpError::BadXPath))
+                }
+                XmpNodeKind::Array(old_form, items) => Some((old_form, items)),
+                _ => return Err(Error::from(c::XmpError::BadXPath)),
+            },
+            None => None,
+        };
+        let (form, mut old_items) =
+            old_items.unwrap_or_else(|| (form.unwrap_or(ArrayForm::Bag), vec![]));
+
+        let items = separate(text, options.contains(ArrayItemsFlags::ALLOW_COMMAS))
+            .into_iter()
+            .map(|value| {
+                // Keep a matching old item, only once.
+                match old_items
+                    .iter()
+                    .position(|item| item.kind == XmpNodeKind::Simple && item.value == value)
+                {
+                    Some(index) => old_items.remove(index),
+                    None => XmpNode::new("", "", &value, PropFlags::NONE),
+                }
+            })
+            .collect();
+        let mut node = XmpNode::new(schema, "", "", PropFlags::from(form));
+        node.kind = XmpNodeKind::Array(form, items);
+
+        let mut xmp = self.clone();
+        xmp.set_node(schema, &name, &node)?;
+        std::mem::swap(self, &mut xmp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{catenate, separate};
+
+    #[test]
+    fn test_catenate() {
+        assert_eq!(
+            catenate(&["a", "b c", "d"], "; ", "\"", false),
+            Ok("a; b c; d".to_owned())
+        );
+        assert_eq!(
+            catenate(&["a;b", "c,d", "e  f", "\"g\""], "", "", false),
+            Ok("\"a;b\"; \"c,d\"; \"e  f\"; \"\"\"g\"\"\"".to_owned())
+        );
+        assert_eq!(
+            catenate(&["c,d"], ";", "\u{00AB}\u{00BB}", true),
+            Ok("c,d".to_owned())
+        );
+        assert_eq!(
+            catenate(&["a;b"], ";", "\u{00AB}\u{00BB}", false),
+            Ok("\u{00AB}a;b\u{00BB}".to_owned())
+        );
+        assert!(catenate(&["a"], ", ", "", false).is_err());
+        assert!(catenate(&["a"], ";;", "", false).is_err());
+        assert!(catenate(&["a"], ";", "ab", false).is_err());
+        assert!(catenate(&["a"], ";", "\u{00AB}\"", false).is_err());
+    }
+
+    #[test]
+    fn test_separate() {
+        assert_eq!(separate("a; b c; d", false), vec!["a", "b c", "d"]);
+        assert_eq!(separate("a, b,c", false), vec!["a", "b", "c"]);
+        assert_eq!(separate("a, b,c", true), vec!["a, b,c"]);
+        assert_eq!(separate(" a  b ;; ", false), vec!["a", "b"]);
+        assert_eq!(separate("", false), Vec::<String>::new());
+
+        let items = ["a;b", "c,d", "e  f", "\"g\"", "h \"i\" j"];
+        let text = catenate(&items, "; ", "\"", false).unwrap();
+        assert_eq!(separate(&text, false), items);
+        let text = catenate(&items, "; ", "\u{00AB}\u{00BB}", false).unwrap();
+        assert_eq!(separate(&text, false), items);
+    }
+}
@@ -0,0 +1,412 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+use std::ffi::CString;
+
+use crate::xmp::{ArrayForm, PropFlags, Xmp};
+use crate::xmpstring::XmpString;
+use crate::DateTime;
+use crate::Result;
+
+/// A property value with its flags, as returned by the `Xmp` getters.
+///
+/// ```no_run
+/// use exempi2::Xmp;
+///
+/// let xmp = Xmp::new();
+/// if let Ok(Some(title)) = xmp.get_property("http://purl.org/dc/elements/1.1/", "title") {
+///     if title.is_array() {
+///         println!("dc:title is an array of {:?}", title.array_form());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Property<T> {
+    /// The value.
+    pub value: T,
+    /// The property flags.
+    pub flags: PropFlags,
+}
+
+impl<T> Property<T> {
+    /// New property with value and flags.
+    pub fn new(value: T, flags: PropFlags) -> Property<T> {
+        Property { value, flags }
+    }
+
+    /// Map the value, keeping the flags.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Property<U> {
+        Property::new(f(self.value), self.flags)
+    }
+
+    /// The property is an array.
+    pub fn is_array(&self) -> bool {
+        self.flags.contains(PropFlags::VALUE_IS_ARRAY)
+    }
+
+    /// The form of the array, `None` if it isn't an array.
+    pub fn array_form(&self) -> Option<ArrayForm> {
+        ArrayForm::from_flags(self.flags)
+    }
+
+    /// The property is a struct.
+    pub fn is_struct(&self) -> bool {
+        self.flags.contains(PropFlags::VALUE_IS_STRUCT)
+    }
+
+    /// The property has an xml:lang qualifier.
+    pub fn has_lang(&self) -> bool {
+        self.flags.contains(PropFlags::HAS_LANG)
+    }
+
+    /// The property has qualifiers.
+    pub fn has_qualifiers(&self) -> bool {
+        self.flags.contains(PropFlags::HAS_QUALIFIERS)
+    }
+
+    /// The property is a qualifier.
+    pub fn is_qualifier(&self) -> bool {
+        self.flags.contains(PropFlags::IS_QUALIFIER)
+    }
+}
+
+/// Types that can be read from a property value.
+///
+/// This is what `Xmp::get()` use. Implement it for your own types,
+/// usually by reading one of the types implemented here.
+///
+/// ```no_run
+/// use exempi2::{Error, FromXmpValue, Property, Result, Xmp, XmpError};
+///
+/// enum Rating {
+///     Rejected,
+///     Stars(i32),
+/// }
+///
+/// impl FromXmpValue for Rating {
+///     fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<Rating>>> {
+///         let property = match i32::from_xmp(xmp, schema, name)? {
+///             Some(property) => property,
+///             None => return Ok(None),
+///         };
+///         let rating = match property.value {
+///             -1 => Rating::Rejected,
+///             n if (0..=5).contains(&n) => Rating::Stars(n),
+///             _ => return Err(Error::from(XmpError::BadValue)),
+///         };
+///         Ok(Some(Property::new(rating, property.flags)))
+///     }
+/// }
+///
+/// let xmp = Xmp::new();
+/// let rating = xmp.get::<Rating>("http://ns.adobe.com/xap/1.0/", "Rating");
+/// ```
+pub trait FromXmpValue: Sized {
+    /// Get the value and the flags of the property `name` in `schema`.
+    /// `name` is a path expression. Return `Ok(None)` if the property
+    /// doesn't exist.
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<Self>>>;
+}
+
+/// Types that can be written as a property value.
+///
+/// This is what `Xmp::set()` use.
+pub trait ToXmpValue {
+    /// Set the value of the property `name` in `schema`.
+    /// `name` is a path expression.
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()>;
+}
+
+impl FromXmpValue for XmpString {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<XmpString>>> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        let mut raw_propsbits = 0u32;
+        let mut property = XmpString::new();
+        let result = unsafe {
+            c::xmp_get_property(
+                xmp.as_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                property.as_mut_ptr(),
+                &mut raw_propsbits,
+            )
+        };
+        if result {
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some(Property::new(property, flags)))
+        } else {
+            crate::absent_or_error()
+        }
+    }
+}
+
+impl FromXmpValue for String {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<String>>> {
+        XmpString::from_xmp(xmp, schema, name)
+            .map(|property| property.map(|property| property.map(|value| String::from(&value))))
+    }
+}
+
+impl FromXmpValue for DateTime {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<DateTime>>> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        let mut raw_propsbits = 0u32;
+        let mut property = DateTime::new();
+        let result = unsafe {
+            c::xmp_get_property_date(
+                xmp.as_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                property.as_mut_ptr(),
+                &mut raw_propsbits,
+            )
+        };
+        if result {
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some(Property::new(property, flags)))
+        } else {
+            crate::absent_or_error()
+        }
+    }
+}
+
+impl FromXmpValue for f64 {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<f64>>> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        let mut raw_propsbits = 0u32;
+        let mut property = 0f64;
+        let result = unsafe {
+            c::xmp_get_property_float(
+                xmp.as_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                &mut property as *mut f64,
+                &mut raw_propsbits,
+            )
+        };
+        if result {
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some(Property::new(property, flags)))
+        } else {
+            crate::absent_or_error()
+        }
+    }
+}
+
+impl FromXmpValue for bool {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<bool>>> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        let mut raw_propsbits = 0u32;
+        let mut property = false;
+        let result = unsafe {
+            c::xmp_get_property_bool(
+                xmp.as_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                &mut property as *mut bool,
+                &mut raw_propsbits,
+            )
+        };
+        if result {
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some(Property::new(property, flags)))
+        } else {
+            crate::absent_or_error()
+        }
+    }
+}
+
+impl FromXmpValue for i32 {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<i32>>> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        let mut raw_propsbits = 0u32;
+        let mut property = 0i32;
+        let result = unsafe {
+            c::xmp_get_property_int32(
+                xmp.as_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                &mut property as *mut i32,
+                &mut raw_propsbits,
+            )
+        };
+        if result {
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some(Property::new(property, flags)))
+        } else {
+            crate::absent_or_error()
+        }
+    }
+}
+
+impl FromXmpValue for i64 {
+    fn from_xmp(xmp: &Xmp, schema: &str, name: &str) -> Result<Option<Property<i64>>> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        let mut raw_propsbits = 0u32;
+        let mut property = 0i64;
+        let result = unsafe {
+            c::xmp_get_property_int64(
+                xmp.as_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                &mut property as *mut i64,
+                &mut raw_propsbits,
+            )
+        };
+        if result {
+            let flags = PropFlags::from_bits(raw_propsbits).unwrap_or_else(PropFlags::empty);
+            Ok(Some(Property::new(property, flags)))
+        } else {
+            crate::absent_or_error()
+        }
+    }
+}
+
+impl ToXmpValue for str {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        let s_value = CString::new(self).unwrap();
+        if unsafe {
+            c::xmp_set_property(
+                xmp.as_mut_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                s_value.as_ptr(),
+                optionbits.bits(),
+            )
+        } {
+            Ok(())
+        } else {
+            Err(crate::get_error())
+        }
+    }
+}
+
+impl ToXmpValue for String {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        self.as_str().to_xmp(xmp, schema, name, optionbits)
+    }
+}
+
+impl ToXmpValue for XmpString {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        String::from(self).to_xmp(xmp, schema, name, optionbits)
+    }
+}
+
+impl ToXmpValue for DateTime {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        if unsafe {
+            c::xmp_set_property_date(
+                xmp.as_mut_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                self.as_ptr(),
+                optionbits.bits(),
+            )
+        } {
+            Ok(())
+        } else {
+            Err(crate::get_error())
+        }
+    }
+}
+
+impl ToXmpValue for f64 {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        if unsafe {
+            c::xmp_set_property_float(
+                xmp.as_mut_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                *self,
+                optionbits.bits(),
+            )
+        } {
+            Ok(())
+        } else {
+            Err(crate::get_error())
+        }
+    }
+}
+
+impl ToXmpValue for bool {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        if unsafe {
+            c::xmp_set_property_bool(
+                xmp.as_mut_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                *self,
+                optionbits.bits(),
+            )
+        } {
+            Ok(())
+        } else {
+            Err(crate::get_error())
+        }
+    }
+}
+
+impl ToXmpValue for i32 {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        if unsafe {
+            c::xmp_set_property_int32(
+                xmp.as_mut_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                *self,
+                optionbits.bits(),
+            )
+        } {
+            Ok(())
+        } else {
+            Err(crate::get_error())
+        }
+    }
+}
+
+impl ToXmpValue for i64 {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        let s_schema = CString::new(schema).unwrap();
+        let s_name = CString::new(name).unwrap();
+        if unsafe {
+            c::xmp_set_property_int64(
+                xmp.as_mut_ptr(),
+                s_schema.as_ptr(),
+                s_name.as_ptr(),
+                *self,
+                optionbits.bits(),
+            )
+        } {
+            Ok(())
+        } else {
+            Err(crate::get_error())
+        }
+    }
+}
+
+impl<T: ToXmpValue + ?Sized> ToXmpValue for &T {
+    fn to_xmp(&self, xmp: &mut Xmp, schema: &str, name: &str, optionbits: PropFlags) -> Result<()> {
+        (**self).to_xmp(xmp, schema, name, optionbits)
+    }
+}
@@ -0,0 +1,77 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+#![cfg(feature = "json")]
+
+use exempi2::{ArrayForm, PropFlags, Xmp};
+
+#[test]
+fn json_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_property(ns, "web", "http://example.com/", PropFlags::VALUE_IS_URI)
+        .is_ok());
+    assert!(xmpblock
+        .set_array(ns, "keywords", ArrayForm::Seq, &["a", "b"])
+        .is_ok());
+    assert!(xmpblock
+        .set_localized_text(ns, "title", "", "x-default", "Hello", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_localized_text(ns, "title", "", "fr", "Bonjour", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_struct_field(ns, "location", ns, "city", "Montréal", PropFlags::NONE)
+        .is_ok());
+
+    let json = xmpblock.to_json();
+    assert!(json.contains("\"alt-text\""));
+    let copy = Xmp::from_json(&json).unwrap();
+    assert_eq!(copy, xmpblock);
+    assert_eq!(copy.to_json(), json);
+    let web = copy.get_property(ns, "web").unwrap().unwrap();
+    assert!(web.flags.contains(PropFlags::VALUE_IS_URI));
+
+    assert_eq!(
+        Xmp::from_json("{").err(),
+        Some(exempi2::Error::from(exempi2::XmpError::BadParse))
+    );
+    assert_eq!(
+        Xmp::from_json("[]").err(),
+        Some(exempi2::Error::from(exempi2::XmpError::BadValue))
+    );
+}
+
+#[test]
+fn json_ld_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_localized_text(ns, "title", "", "x-default", "Hello", PropFlags::NONE)
+        .is_ok());
+
+    let json = xmpblock.to_json_ld("urn:test");
+    assert_eq!(json["@context"]["rust"], ns);
+    assert_eq!(
+        json["@context"]["rdf"],
+        "http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+    );
+    assert_eq!(json["@id"], "urn:test");
+    assert_eq!(json["rust:title"]["@type"], "rdf:Alt");
+    assert_eq!(
+        json["rust:title"]["rdf:_1"],
+        serde_json::json!({"@value": "Hello", "@language": "x-default"})
+    );
+}
@@ -44,11 +44,11 @@ fn libary_tests() {
         )
         .is_ok());
     assert!(xmpblock.has_property("http://rust.figuiere.net/ns/rust/", "test"));
-    let mut optionbits: PropFlags = PropFlags::NONE;
-    let value = xmpblock.get_property("http://rust.figuiere.net/ns/rust/", "test", &mut optionbits);
+    let value = xmpblock.get_property("http://rust.figuiere.net/ns/rust/", "test");
     assert!(value.is_ok());
-    assert!(value.unwrap().to_str() == Ok("foobar"));
-    assert!(optionbits == PropFlags::NONE);
+    let value = value.unwrap().unwrap();
+    assert!(value.value.to_str() == Ok("foobar"));
+    assert!(value.flags == PropFlags::NONE);
 
     let result = xmpblock.serialize(
         SerialFlags::OMITPACKETWRAPPER | SerialFlags::USECOMPACTFORMAT,
@@ -57,3 +57,874 @@ fn libary_tests() {
     assert!(result.is_ok());
     println!("{}", result.unwrap().to_string());
 }
+
+#[test]
+fn struct_field_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_struct_field(
+            ns,
+            "contact",
+            ns,
+            "email",
+            "hub@example.com",
+            PropFlags::NONE
+        )
+        .is_ok());
+    assert!(xmpblock.has_property(ns, XmpPath::new("contact").field(ns, "email")));
+    let value = xmpblock.get_struct_field(ns, "contact", ns, "email");
+    assert_eq!(
+        value.unwrap().unwrap().value.to_str(),
+        Ok("hub@example.com")
+    );
+    assert!(xmpblock
+        .set_struct_field_i32(ns, "contact", ns, "zip", 12345, PropFlags::NONE)
+        .is_ok());
+    let value = xmpblock.get_struct_field_i32(ns, "contact", ns, "zip");
+    assert_eq!(value.map(|v| v.map(|v| v.value)), Ok(Some(12345)));
+    assert!(xmpblock
+        .delete_struct_field(ns, "contact", ns, "email")
+        .is_ok());
+    assert!(!xmpblock.has_property(ns, XmpPath::new("contact").field(ns, "email")));
+
+    // a qualifier can't be set on a property that doesn't exist.
+    assert!(xmpblock
+        .set_qualifier(ns, "test", ns, "kind", "foo", PropFlags::NONE)
+        .is_err());
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_qualifier(ns, "test", ns, "kind", "foo", PropFlags::NONE)
+        .is_ok());
+    let value = xmpblock
+        .get_qualifier(ns, "test", ns, "kind")
+        .unwrap()
+        .unwrap();
+    assert_eq!(value.value.to_str(), Ok("foo"));
+    assert!(value.is_qualifier());
+    assert!(xmpblock.delete_qualifier(ns, "test", ns, "kind").is_ok());
+    assert!(!xmpblock.has_property(ns, XmpPath::new("test").qualifier(ns, "kind")));
+}
+
+#[test]
+fn array_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert_eq!(xmpblock.count_array_items(ns, "keywords"), Ok(0));
+    for keyword in ["a", "b", "c"] {
+        assert!(xmpblock
+            .append_array_item(
+                ns,
+                "keywords",
+                PropFlags::VALUE_IS_ARRAY | PropFlags::ARRAY_IS_ORDERED,
+                keyword,
+                PropFlags::NONE
+            )
+            .is_ok());
+    }
+    assert_eq!(xmpblock.count_array_items(ns, "keywords"), Ok(3));
+
+    let value = xmpblock.get_array_item(ns, "keywords", ARRAY_LAST_ITEM);
+    assert_eq!(value.unwrap().unwrap().value.to_str(), Ok("c"));
+
+    assert!(xmpblock
+        .insert_array_item(
+            ns,
+            "keywords",
+            1,
+            InsertPosition::Before,
+            "z",
+            PropFlags::NONE
+        )
+        .is_ok());
+    assert!(xmpblock.move_array_item(ns, "keywords", 1, 4).is_ok());
+    assert!(xmpblock.delete_array_item(ns, "keywords", 1).is_ok());
+    assert_eq!(xmpblock.count_array_items(ns, "keywords"), Ok(3));
+    let value = xmpblock.get_array_item(ns, "keywords", 1);
+    assert_eq!(value.unwrap().unwrap().value.to_str(), Ok("b"));
+    let value = xmpblock.get_array_item(ns, "keywords", 3);
+    assert_eq!(value.unwrap().unwrap().value.to_str(), Ok("z"));
+
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock.count_array_items(ns, "test").is_err());
+}
+
+#[test]
+fn whole_array_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert_eq!(
+        xmpblock.get_array::<String>(ns, "subject"),
+        Ok(Vec::<String>::new())
+    );
+    assert!(xmpblock
+        .set_array(ns, "subject", ArrayForm::Bag, &["a", "b", "c"])
+        .is_ok());
+    let value = xmpblock.get_property(ns, "subject").unwrap().unwrap();
+    assert_eq!(value.array_form(), Some(ArrayForm::Bag));
+    let subject = xmpblock.get_array::<String>(ns, "subject").unwrap();
+    assert_eq!(subject, vec!["a", "b", "c"]);
+
+    assert!(xmpblock
+        .set_array(ns, "ratings", ArrayForm::Seq, &[1, 2, 3])
+        .is_ok());
+    assert_eq!(xmpblock.get_array::<i32>(ns, "ratings"), Ok(vec![1, 2, 3]));
+    // An item that can't be read isn't skipped.
+    assert!(xmpblock
+        .set_array(ns, "ratings", ArrayForm::Seq, &["1", "x", "3"])
+        .is_ok());
+    assert!(xmpblock.get_array::<i32>(ns, "ratings").is_err());
+
+    assert!(xmpblock
+        .set_array(ns, "subject", ArrayForm::Seq, &["d"])
+        .is_ok());
+    assert_eq!(xmpblock.count_array_items(ns, "subject"), Ok(1));
+    assert!(xmpblock
+        .set_array(ns, "subject", ArrayForm::AltText, &["e"])
+        .is_err());
+    assert_eq!(xmpblock.count_array_items(ns, "subject"), Ok(1));
+}
+
+#[test]
+fn generic_value_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock.set(ns, "count", &42i32).is_ok());
+    assert_eq!(xmpblock.get::<i32>(ns, "count"), Ok(Some(42)));
+    assert_eq!(
+        xmpblock.get::<String>(ns, "count"),
+        Ok(Some("42".to_owned()))
+    );
+    assert!(xmpblock.set(ns, "flag", &true).is_ok());
+    assert_eq!(xmpblock.get::<bool>(ns, "flag"), Ok(Some(true)));
+    assert!(xmpblock.set(ns, "test", "foobar").is_ok());
+    assert!(xmpblock.get::<i64>(ns, "test").is_err());
+}
+
+#[test]
+fn absent_property_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert_eq!(xmpblock.get_property(ns, "test"), Ok(None));
+    assert_eq!(xmpblock.get_property_i32(ns, "test"), Ok(None));
+    assert_eq!(xmpblock.get::<String>(ns, "test"), Ok(None));
+    assert_eq!(xmpblock.get_array_item(ns, "test", 1), Ok(None));
+    assert_eq!(
+        xmpblock.get_localized_text(ns, "test", "", "x-default"),
+        Ok(None)
+    );
+
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock.get_property(ns, "test").unwrap().is_some());
+    // An absent property in an unregistered namespace is an error.
+    assert!(xmpblock
+        .get_property("http://rust.figuiere.net/ns/unknown/", "test")
+        .is_err());
+}
+
+#[test]
+fn geo_point_tests() {
+    let mut xmpblock = Xmp::new();
+    assert_eq!(xmpblock.get_geo_point(), Ok(None));
+
+    let point = GeoPoint {
+        latitude: -33.8688,
+        longitude: 151.2093,
+        altitude: Some(-12.5),
+        timestamp: None,
+    };
+    assert!(xmpblock.set_geo_point(&point).is_ok());
+    let value = xmpblock.get_property("http://ns.adobe.com/exif/1.0/", "GPSLatitude");
+    assert_eq!(value.unwrap().unwrap().value.to_str(), Ok("33,52.128S"));
+
+    let result = xmpblock.get_geo_point().unwrap().unwrap();
+    assert!((result.latitude - point.latitude).abs() < 1e-6);
+    assert!((result.longitude - point.longitude).abs() < 1e-6);
+    assert_eq!(result.altitude, Some(-12.5));
+    assert_eq!(result.timestamp, None);
+
+    // On error the position is left untouched.
+    let moved = GeoPoint {
+        latitude: 45.5017,
+        longitude: -73.5673,
+        altitude: Some(f64::NAN),
+        timestamp: None,
+    };
+    assert_eq!(
+        xmpblock.set_geo_point(&moved),
+        Err(Error::from(exempi_sys::XmpError::BadValue))
+    );
+    let unchanged = xmpblock.get_geo_point().unwrap().unwrap();
+    assert_eq!(unchanged.latitude, result.latitude);
+    assert_eq!(unchanged.longitude, result.longitude);
+
+    // Without GPSAltitudeRef the altitude is above sea level.
+    let exif = "http://ns.adobe.com/exif/1.0/";
+    assert!(xmpblock.delete_property(exif, "GPSAltitudeRef").is_ok());
+    let result = xmpblock.get_geo_point().unwrap().unwrap();
+    assert_eq!(result.altitude, Some(12.5));
+    // An invalid GPSAltitudeRef is an error.
+    assert!(xmpblock
+        .set_property(exif, "GPSAltitudeRef", "below", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock.get_geo_point().is_err());
+}
+
+#[test]
+fn alt_text_map_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert_eq!(xmpblock.get_alt_text_map(ns, "title"), Ok(vec![]));
+    assert!(xmpblock
+        .set_alt_text_map(
+            ns,
+            "title",
+            &[("fr", "Bonjour"), ("x-default", "Hello"), ("en", "Hello")]
+        )
+        .is_ok());
+    let entries = xmpblock.get_alt_text_map(ns, "title").unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            ("x-default".to_owned(), "Hello".to_owned()),
+            ("fr".to_owned(), "Bonjour".to_owned()),
+            ("en".to_owned(), "Hello".to_owned()),
+        ]
+    );
+
+    assert!(xmpblock
+        .promote_alt_text_language(ns, "title", "fr")
+        .is_ok());
+    let entries = xmpblock.get_alt_text_map(ns, "title").unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0], ("x-default".to_owned(), "Bonjour".to_owned()));
+    assert!(xmpblock
+        .promote_alt_text_language(ns, "title", "de")
+        .is_err());
+
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock.get_alt_text_map(ns, "test").is_err());
+}
+
+#[test]
+fn tree_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_qualifier(ns, "test", ns, "kind", "foo", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_struct_field(
+            ns,
+            "contact",
+            ns,
+            "email",
+            "hub@example.com",
+            PropFlags::NONE
+        )
+        .is_ok());
+    assert!(xmpblock
+        .set_array(ns, "subject", ArrayForm::Seq, &["a", "b"])
+        .is_ok());
+    assert!(xmpblock
+        .set_alt_text_map(ns, "title", &[("x-default", "Hello"), ("fr", "Bonjour")])
+        .is_ok());
+
+    let tree = xmpblock.to_tree();
+    let schema = tree.schema(ns).unwrap();
+    assert_eq!(schema.prefix, "rust");
+    assert_eq!(schema.properties.len(), 4);
+
+    let test = schema.property("test").unwrap();
+    assert_eq!(test.kind, XmpNodeKind::Simple);
+    assert_eq!(test.value, "foobar");
+    assert_eq!(test.qualifier(ns, "kind").unwrap().value, "foo");
+
+    let contact = schema.property("contact").unwrap();
+    assert_eq!(contact.field(ns, "email").unwrap().value, "hub@example.com");
+
+    let subject = schema.property("subject").unwrap();
+    assert_eq!(subject.array_form(), Some(ArrayForm::Seq));
+    let items: Vec<&str> = subject
+        .children()
+        .iter()
+        .map(|i| i.value.as_str())
+        .collect();
+    assert_eq!(items, vec!["a", "b"]);
+
+    let title = schema.property("title").unwrap();
+    assert_eq!(title.array_form(), Some(ArrayForm::AltText));
+    let lang = title.children()[1]
+        .qualifier("http://www.w3.org/XML/1998/namespace", "lang")
+        .unwrap();
+    assert_eq!(lang.value, "fr");
+
+    let copy = Xmp::from_tree(&tree).unwrap();
+    assert_eq!(copy.to_tree(), tree);
+}
+
+#[test]
+fn diff_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut on_disk = Xmp::new();
+    assert!(on_disk
+        .set_alt_text_map(ns, "title", &[("x-default", "Hello"), ("fr", "Bonjour")])
+        .is_ok());
+    let mut edited = on_disk.clone();
+    assert!(on_disk.diff(&edited).is_empty());
+
+    assert!(edited
+        .set_alt_text_map(ns, "title", &[("x-default", "Hello"), ("fr", "Salut")])
+        .is_ok());
+    let changes = on_disk.diff(&edited);
+    assert_eq!(
+        changes,
+        vec![XmpChange::Modified {
+            schema: ns.to_owned(),
+            path: "rust:title[?xml:lang=\"fr\"]".to_owned(),
+            old: "Bonjour".to_owned(),
+            new: "Salut".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn merge_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut camera = Xmp::new();
+    assert!(camera
+        .set_property(ns, "test", "foo", PropFlags::NONE)
+        .is_ok());
+    assert!(camera
+        .set_array(ns, "subject", ArrayForm::Bag, &["a", "b"])
+        .is_ok());
+    assert!(camera
+        .set_alt_text_map(ns, "title", &[("x-default", "Hello")])
+        .is_ok());
+
+    let mut template = Xmp::new();
+    assert!(template
+        .set_property(ns, "test", "bar", PropFlags::NONE)
+        .is_ok());
+    assert!(template
+        .set_property(ns, "other", "baz", PropFlags::NONE)
+        .is_ok());
+    assert!(template
+        .set_array(ns, "subject", ArrayForm::Bag, &["b", "c"])
+        .is_ok());
+    assert!(template
+        .set_alt_text_map(ns, "title", &[("x-default", "Hi"), ("fr", "Bonjour")])
+        .is_ok());
+
+    assert!(camera
+        .merge(
+            &template,
+            MergePolicy::new(MergeConflict::KeepExisting)
+                .union_bags(true)
+                .merge_alt_text(true)
+        )
+        .is_ok());
+    assert_eq!(camera.get::<String>(ns, "test"), Ok(Some("foo".to_owned())));
+    assert_eq!(
+        camera.get::<String>(ns, "other"),
+        Ok(Some("baz".to_owned()))
+    );
+    assert_eq!(
+        camera.get_array::<String>(ns, "subject"),
+        Ok(vec!["a".to_owned(), "b".to_owned(), "c".to_owned()])
+    );
+    assert_eq!(
+        camera.get_alt_text_map(ns, "title"),
+        Ok(vec![
+            ("x-default".to_owned(), "Hello".to_owned()),
+            ("fr".to_owned(), "Bonjour".to_owned())
+        ])
+    );
+
+    assert!(camera
+        .merge(&template, MergePolicy::new(MergeConflict::Overwrite))
+        .is_ok());
+    assert_eq!(camera.get::<String>(ns, "test"), Ok(Some("bar".to_owned())));
+    assert_eq!(camera.count_array_items(ns, "subject"), Ok(2));
+}
+
+#[test]
+fn remove_schema_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    let ns_dc = "http://purl.org/dc/elements/1.1/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_struct_field(
+            ns,
+            "contact",
+            ns,
+            "email",
+            "hub@example.com",
+            PropFlags::NONE
+        )
+        .is_ok());
+    assert!(xmpblock
+        .set_array(ns_dc, "subject", ArrayForm::Bag, &["a", "b"])
+        .is_ok());
+
+    let mut copy = xmpblock.clone();
+    assert!(copy.retain_schemas(&[ns_dc], RemoveFlags::NONE).is_ok());
+    assert!(!copy.has_property(ns, "test"));
+    assert!(copy.has_property(ns_dc, "subject"));
+
+    assert!(xmpblock.remove_schema(ns, RemoveFlags::NONE).is_ok());
+    assert!(!xmpblock.has_property(ns, "test"));
+    assert!(!xmpblock.has_property(ns, "contact"));
+    assert!(xmpblock.has_property(ns_dc, "subject"));
+}
+
+#[test]
+fn copy_subtree_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut template = Xmp::new();
+    assert!(template
+        .set_struct_field(
+            ns,
+            "contact",
+            ns,
+            "email",
+            "hub@example.com",
+            PropFlags::NONE
+        )
+        .is_ok());
+    assert!(template
+        .set_qualifier(ns, "contact", ns, "kind", "work", PropFlags::NONE)
+        .is_ok());
+    assert!(template
+        .set_array(
+            ns,
+            XmpPath::new("contact").field(ns, "phones"),
+            ArrayForm::Seq,
+            &["1", "2"]
+        )
+        .is_ok());
+
+    let node = template.get_node(ns, "contact").unwrap().unwrap();
+    assert_eq!(node.name, "contact");
+    assert_eq!(node.field(ns, "email").unwrap().value, "hub@example.com");
+    assert_eq!(template.get_node(ns, "missing"), Ok(None));
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .copy_subtree(&template, ns, "contact", ns, "owner")
+        .is_ok());
+    assert_eq!(
+        xmpblock
+            .get_struct_field(ns, "owner", ns, "email")
+            .unwrap()
+            .unwrap()
+            .value
+            .to_str(),
+        Ok("hub@example.com")
+    );
+    assert_eq!(
+        xmpblock
+            .get_qualifier(ns, "owner", ns, "kind")
+            .unwrap()
+            .unwrap()
+            .value
+            .to_str(),
+        Ok("work")
+    );
+    assert_eq!(
+        xmpblock.get_array::<String>(ns, XmpPath::new("owner").field(ns, "phones")),
+        Ok(vec!["1".to_owned(), "2".to_owned()])
+    );
+    assert!(xmpblock
+        .copy_subtree(&template, ns, "missing", ns, "owner")
+        .is_err());
+    assert!(xmpblock.has_property(ns, "owner"));
+}
+
+#[test]
+fn catenate_array_items_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert_eq!(
+        xmpblock.catenate_array_items(ns, "keywords", "; ", "\"", ArrayItemsFlags::NONE),
+        Ok(String::new())
+    );
+    assert!(xmpblock
+        .separate_array_items(
+            ns,
+            "keywords",
+            PropFlags::NONE,
+            ArrayItemsFlags::NONE,
+            "a; b c; \"d;e\""
+        )
+        .is_ok());
+    let value = xmpblock.get_property(ns, "keywords").unwrap().unwrap();
+    assert_eq!(value.array_form(), Some(ArrayForm::Bag));
+    assert_eq!(
+        xmpblock.get_array::<String>(ns, "keywords"),
+        Ok(vec!["a".to_owned(), "b c".to_owned(), "d;e".to_owned()])
+    );
+    assert_eq!(
+        xmpblock.catenate_array_items(ns, "keywords", "; ", "\"", ArrayItemsFlags::NONE),
+        Ok("a; b c; \"d;e\"".to_owned())
+    );
+
+    // The form must match the existing array.
+    assert!(xmpblock
+        .separate_array_items(
+            ns,
+            "keywords",
+            PropFlags::from(ArrayForm::Seq),
+            ArrayItemsFlags::NONE,
+            "a"
+        )
+        .is_err());
+    assert_eq!(xmpblock.count_array_items(ns, "keywords"), Ok(3));
+
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .catenate_array_items(ns, "test", "; ", "\"", ArrayItemsFlags::NONE)
+        .is_err());
+}
+
+#[test]
+fn dump_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert_eq!(xmpblock.to_string(), "");
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_localized_text(ns, "title", "", "x-default", "Hello", PropFlags::NONE)
+        .is_ok());
+
+    let dump = xmpblock.to_string();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines[0], "http://rust.figuiere.net/ns/rust/ (rust:)");
+    assert_eq!(lines[1], "  rust:test = \"foobar\"");
+    assert!(lines[2].starts_with("  rust:title (AltText) [VALUE_IS_ARRAY"));
+    assert_eq!(lines[3], "    [1] = \"Hello\" [HAS_QUALIFIERS | HAS_LANG]");
+    assert_eq!(lines[4], "      ?xml:lang = \"x-default\" [IS_QUALIFIER]");
+    assert_eq!(format!("{:?}", xmpblock), dump);
+    assert!(format!("{:#?}", xmpblock).contains("(0x00000000)"));
+}
+
+#[test]
+fn equality_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let a = Xmp::from_buffer(
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+         <rdf:Description rdf:about=\"\" xmlns:rust=\"http://rust.figuiere.net/ns/rust/\">\
+         <rust:test>foo</rust:test>\
+         <rust:keywords><rdf:Bag><rdf:li>a</rdf:li><rdf:li>b</rdf:li></rdf:Bag></rust:keywords>\
+         </rdf:Description></rdf:RDF></x:xmpmeta>",
+    )
+    .unwrap();
+    let b = Xmp::from_buffer(
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+         <rdf:Description rdf:about=\"\" xmlns:rust=\"http://rust.figuiere.net/ns/rust/\">\n\
+         \x20 <rust:keywords>\n\
+         \x20   <rdf:Bag><rdf:li>b</rdf:li><rdf:li>a</rdf:li></rdf:Bag>\n\
+         \x20 </rust:keywords>\n\
+         \x20 <rust:test>foo</rust:test>\n\
+         </rdf:Description></rdf:RDF></x:xmpmeta>",
+    )
+    .unwrap();
+    assert_eq!(a, b);
+    assert_eq!(a.content_hash(), b.content_hash());
+    assert_eq!(a, a.clone());
+
+    let mut c = b.clone();
+    assert!(c.set_property(ns, "test", "bar", PropFlags::NONE).is_ok());
+    assert_ne!(a, c);
+    assert_ne!(a.content_hash(), c.content_hash());
+}
+
+#[test]
+fn canonical_serialization_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    let ns_dc = "http://purl.org/dc/elements/1.1/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut a = Xmp::new();
+    assert!(a.set_property(ns, "test", "foo", PropFlags::NONE).is_ok());
+    assert!(a
+        .set_array(ns, "keywords", ArrayForm::Bag, &["a", "b"])
+        .is_ok());
+    assert!(a
+        .set_property(ns_dc, "format", "image/jpeg", PropFlags::NONE)
+        .is_ok());
+    let mut b = Xmp::new();
+    assert!(b
+        .set_property(ns_dc, "format", "image/jpeg", PropFlags::NONE)
+        .is_ok());
+    assert!(b
+        .set_array(ns, "keywords", ArrayForm::Bag, &["b", "a"])
+        .is_ok());
+    assert!(b.set_property(ns, "test", "foo", PropFlags::NONE).is_ok());
+
+    let options = SerialFlags::OMITPACKETWRAPPER;
+    assert_ne!(
+        a.serialize(options, 0).unwrap().to_str(),
+        b.serialize(options, 0).unwrap().to_str()
+    );
+    let canonical = a.serialize_canonical(options, 0).unwrap();
+    assert_eq!(
+        canonical.to_str(),
+        b.serialize_canonical(options, 0).unwrap().to_str()
+    );
+    assert_eq!(
+        a.serialize_canonical_and_format(options, 0, "\n", " ", 0)
+            .unwrap()
+            .to_str(),
+        b.serialize_canonical_and_format(options, 0, "\n", " ", 0)
+            .unwrap()
+            .to_str()
+    );
+    assert_eq!(a.canonical().unwrap(), a);
+}
+
+#[test]
+fn serialize_to_bytes_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+
+    let options = SerialOptions::new().flags(SerialFlags::OMITPACKETWRAPPER);
+    let utf8 = xmpblock.serialize_to_bytes(&options).unwrap();
+    assert_eq!(
+        Some(utf8.as_slice()),
+        xmpblock
+            .serialize(SerialFlags::OMITPACKETWRAPPER, 0)
+            .ok()
+            .as_ref()
+            .map(|s| s.as_bytes())
+    );
+
+    let utf16 = xmpblock
+        .serialize_to_bytes(
+            &options
+                .clone()
+                .flags(SerialFlags::OMITPACKETWRAPPER | SerialFlags::ENCODEUTF16BIG),
+        )
+        .unwrap();
+    assert_eq!(utf16.len(), utf8.len() * 2);
+    let units: Vec<u16> = utf16
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+    assert_eq!(String::from_utf16(&units).unwrap().as_bytes(), &utf8[..]);
+
+    let mut written = vec![];
+    assert!(xmpblock.serialize_to_writer(&options, &mut written).is_ok());
+    assert_eq!(written, utf8);
+
+    let crlf = xmpblock
+        .serialize_to_bytes(&options.newline("\r\n"))
+        .unwrap();
+    assert!(String::from_utf8(crlf).unwrap().contains("\r\n"));
+}
+
+#[test]
+fn rdf_export_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_array(ns, "keywords", ArrayForm::Seq, &["a", "b"])
+        .is_ok());
+
+    let ntriples = xmpblock.to_ntriples("urn:test");
+    let lines: Vec<&str> = ntriples.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "<urn:test> <http://rust.figuiere.net/ns/rust/test> \"foobar\" .",
+            "<urn:test> <http://rust.figuiere.net/ns/rust/keywords> _:b1 .",
+            "_:b1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> \
+             <http://www.w3.org/1999/02/22-rdf-syntax-ns#Seq> .",
+            "_:b1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#_1> \"a\" .",
+            "_:b1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#_2> \"b\" .",
+        ]
+    );
+
+    let turtle = xmpblock.to_turtle("urn:test");
+    assert!(turtle.contains("@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> ."));
+    assert!(turtle.contains("@prefix rust: <http://rust.figuiere.net/ns/rust/> ."));
+    assert!(turtle.contains("_:b1 rdf:type rdf:Seq ;\n    rdf:_1 \"a\" ;\n    rdf:_2 \"b\" .\n"));
+}
+
+#[test]
+fn flat_map_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert!(xmpblock
+        .set_property(ns, "test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock
+        .set_array(ns, "keywords", ArrayForm::Bag, &["a", "b"])
+        .is_ok());
+    assert!(xmpblock
+        .set_struct_field(
+            ns,
+            "contact",
+            ns,
+            "email",
+            "hub@example.com",
+            PropFlags::NONE
+        )
+        .is_ok());
+    assert!(xmpblock
+        .set_localized_text(ns, "title", "", "x-default", "Hello", PropFlags::NONE)
+        .is_ok());
+
+    let map = xmpblock.to_flat_map();
+    assert_eq!(map.get("rust:test").map(String::as_str), Some("foobar"));
+    assert_eq!(map.get("rust:keywords[2]").map(String::as_str), Some("b"));
+    assert_eq!(
+        map.get("rust:contact/rust:email").map(String::as_str),
+        Some("hub@example.com")
+    );
+    assert_eq!(
+        map.get("rust:title[?xml:lang=\"x-default\"]")
+            .map(String::as_str),
+        Some("Hello")
+    );
+
+    let rebuilt = Xmp::from_flat_map(&map).unwrap();
+    assert_eq!(rebuilt, xmpblock);
+    assert_eq!(rebuilt.to_flat_map(), map);
+
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("nosuchprefix:test".to_owned(), "foo".to_owned());
+    assert_eq!(
+        Xmp::from_flat_map(&map),
+        Err(Error::from(exempi_sys::XmpError::BadSchema))
+    );
+}
+
+#[test]
+fn prefixed_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    let mut xmpblock = Xmp::new();
+    assert_eq!(xmpblock.has_property_prefixed("rust:test"), Ok(false));
+    assert!(xmpblock
+        .set_property_prefixed("rust:test", "foobar", PropFlags::NONE)
+        .is_ok());
+    assert!(xmpblock.has_property(ns, "test"));
+    assert_eq!(xmpblock.has_property_prefixed("rust:test"), Ok(true));
+    assert_eq!(
+        xmpblock
+            .get_property_prefixed("rust:test")
+            .unwrap()
+            .map(|p| String::from(&p.value)),
+        Some("foobar".to_owned())
+    );
+
+    assert!(xmpblock
+        .set_prefixed("rust:contact/rust:zip", &12345)
+        .is_ok());
+    assert_eq!(
+        xmpblock.get_prefixed::<i32>("rust:contact/rust:zip"),
+        Ok(Some(12345))
+    );
+    assert_eq!(
+        xmpblock
+            .get_struct_field_i32(ns, "contact", ns, "zip")
+            .map(|v| v.map(|v| v.value)),
+        Ok(Some(12345))
+    );
+
+    let names: Vec<String> = XmpIterator::new_prefixed(&xmpblock, "rust:", IterFlags::PROPERTIES)
+        .unwrap()
+        .map(|v| String::from(&v.name))
+        .collect();
+    assert!(names.contains(&"rust:test".to_owned()));
+    assert!(XmpIterator::new_prefixed(&xmpblock, "rust:contact", IterFlags::PROPERTIES).is_ok());
+
+    assert!(xmpblock.delete_property_prefixed("rust:test").is_ok());
+    assert!(!xmpblock.has_property(ns, "test"));
+
+    // Unknown prefixes and unprefixed names are errors.
+    assert_eq!(
+        xmpblock.get_prefixed::<String>("nosuchprefix:test"),
+        Err(Error::from(exempi_sys::XmpError::BadSchema))
+    );
+    assert_eq!(
+        xmpblock.has_property_prefixed("rust:contact/nosuchprefix:zip"),
+        Err(Error::from(exempi_sys::XmpError::BadSchema))
+    );
+    assert_eq!(
+        xmpblock.has_property_prefixed("rust:contact[nosuchprefix:zip=\"1\"]"),
+        Err(Error::from(exempi_sys::XmpError::BadSchema))
+    );
+    assert!(XmpIterator::new_prefixed(&xmpblock, "nosuchprefix:", IterFlags::PROPERTIES).is_err());
+    // A '/' in a quoted selector value doesn't start a step.
+    assert_eq!(
+        xmpblock.has_property_prefixed("rust:bag[rust:url=\"http://example.com/\"]"),
+        Ok(false)
+    );
+    assert_eq!(
+        xmpblock.delete_property_prefixed("test"),
+        Err(Error::from(exempi_sys::XmpError::BadXPath))
+    );
+}
@@ -0,0 +1,180 @@
+//
+// Copyright (c) 2016-2021, Hubert Figuière
+//
+// License: BSD-2-Clause
+// See top-level LICENSE file.
+//
+
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use exempi2::xmpserde::Error;
+use exempi2::{ArrayForm, PropFlags, Xmp};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+struct Location {
+    city: String,
+    #[serde(rename = "dc:description")]
+    description: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "rust")]
+struct Meta {
+    title: String,
+    count: i32,
+    enabled: bool,
+    keywords: Vec<String>,
+    #[serde(with = "exempi2::xmpserde::seq")]
+    steps: Vec<u32>,
+    label: BTreeMap<String, String>,
+    location: Location,
+    #[serde(rename = "xmp:Rating")]
+    rating: Option<i32>,
+}
+
+#[test]
+fn serde_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+    let ns_xap = "http://ns.adobe.com/xap/1.0/";
+
+    let mut label = BTreeMap::new();
+    label.insert("x-default".to_owned(), "Hello".to_owned());
+    label.insert("fr".to_owned(), "Bonjour".to_owned());
+    let meta = Meta {
+        title: "foo".to_owned(),
+        count: 42,
+        enabled: true,
+        keywords: vec!["a".to_owned(), "b".to_owned()],
+        steps: vec![3, 1, 2],
+        label,
+        location: Location {
+            city: "Montréal".to_owned(),
+            description: None,
+        },
+        rating: Some(5),
+    };
+
+    let mut xmp = Xmp::new();
+    assert!(exempi2::to_xmp(&meta, &mut xmp).is_ok());
+    assert_eq!(xmp.get::<String>(ns, "title"), Ok(Some("foo".to_owned())));
+    assert_eq!(xmp.get::<i32>(ns, "count"), Ok(Some(42)));
+    assert_eq!(xmp.get::<bool>(ns, "enabled"), Ok(Some(true)));
+    let keywords = xmp.get_property(ns, "keywords").unwrap().unwrap();
+    assert_eq!(keywords.array_form(), Some(ArrayForm::Bag));
+    let steps = xmp.get_property(ns, "steps").unwrap().unwrap();
+    assert_eq!(steps.array_form(), Some(ArrayForm::Seq));
+    assert_eq!(xmp.get_array::<i32>(ns, "steps"), Ok(vec![3, 1, 2]));
+    assert_eq!(
+        xmp.get_alt_text_map(ns, "label"),
+        Ok(vec![
+            ("x-default".to_owned(), "Hello".to_owned()),
+            ("fr".to_owned(), "Bonjour".to_owned())
+        ])
+    );
+    assert_eq!(
+        xmp.get_struct_field(ns, "location", ns, "city")
+            .unwrap()
+            .map(|p| String::from(&p.value)),
+        Some("Montréal".to_owned())
+    );
+    assert!(!xmp.has_property(ns, "location/dc:description"));
+    assert_eq!(xmp.get::<i32>(ns_xap, "Rating"), Ok(Some(5)));
+
+    let back: Meta = exempi2::from_xmp(&xmp).unwrap();
+    assert_eq!(back, meta);
+
+    // None delete the property.
+    let meta = Meta {
+        rating: None,
+        location: Location {
+            city: "Paris".to_owned(),
+            description: Some("Ville lumière".to_owned()),
+        },
+        ..meta
+    };
+    assert!(exempi2::to_xmp(&meta, &mut xmp).is_ok());
+    assert!(!xmp.has_property(ns_xap, "Rating"));
+    assert!(xmp.has_property(ns, "location/dc:description"));
+    let back: Meta = exempi2::from_xmp(&xmp).unwrap();
+    assert_eq!(back, meta);
+
+    // Type mismatch.
+    assert!(xmp
+        .set_property(ns, "count", "many", PropFlags::NONE)
+        .is_ok());
+    assert!(exempi2::from_xmp::<Meta>(&xmp).is_err());
+
+    // Unknown prefix.
+    #[derive(Serialize)]
+    struct Unknown {
+        #[serde(rename = "nope:field")]
+        field: String,
+    }
+    let unknown = Unknown {
+        field: "value".to_owned(),
+    };
+    assert_eq!(
+        exempi2::to_xmp(&unknown, &mut xmp),
+        Err(Error::Xmp(exempi2::Error::from(
+            exempi2::XmpError::BadSchema
+        )))
+    );
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+enum Mode {
+    Auto,
+    Manual(i32),
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "rust")]
+struct Settings {
+    mode: Mode,
+}
+
+#[test]
+fn serde_error_tests() {
+    let ns = "http://rust.figuiere.net/ns/rust/";
+    assert!(exempi2::register_namespace(ns, "rust").is_ok());
+
+    // The serde errors keep their message.
+    let mut xmp = Xmp::new();
+    let err = exempi2::from_xmp::<Settings>(&xmp).unwrap_err();
+    assert_eq!(err, Error::Message("missing field `mode`".to_owned()));
+    assert_eq!(err.to_string(), "missing field `mode`");
+    assert_eq!(
+        exempi2::Error::from(err),
+        exempi2::Error::from(exempi2::XmpError::BadValue)
+    );
+
+    // Unit variants are simple values.
+    let settings = Settings { mode: Mode::Auto };
+    assert!(exempi2::to_xmp(&settings, &mut xmp).is_ok());
+    assert_eq!(xmp.get::<String>(ns, "mode"), Ok(Some("Auto".to_owned())));
+    assert_eq!(exempi2::from_xmp::<Settings>(&xmp), Ok(settings));
+
+    // Variants with data aren't supported.
+    let settings = Settings {
+        mode: Mode::Manual(2),
+    };
+    assert!(matches!(
+        exempi2::to_xmp(&settings, &mut xmp),
+        Err(Error::Unsupported(_))
+    ));
+    assert!(matches!(
+        exempi2::to_xmp(&Mode::Auto, &mut xmp),
+        Err(Error::Unsupported(_))
+    ));
+    assert!(xmp
+        .set_property(ns, "mode", "Manual", PropFlags::NONE)
+        .is_ok());
+    assert!(matches!(
+        exempi2::from_xmp::<Settings>(&xmp),
+        Err(Error::Unsupported(_))
+    ));
+}